@@ -1,19 +1,43 @@
 use eframe::egui;
 
-use std::io::{ BufReader, BufRead };
 use std::sync::{ Arc, Mutex };
-use std::time::Duration;
-use tokio::task;
 use std::rc::Rc;
 
+use crate::components::connection::{
+    connection_status_ui,
+    connection_tab_ui,
+    ConnectionAction,
+};
+use crate::components::find::find_bar_ui;
+use crate::components::highlights::styled_line;
 use crate::components::logger::Logger;
-use crate::components::project::{Project, project_tab_ui};
+use crate::components::plot::plot_tab_ui;
+use crate::components::project::{ Project, ProjectAction, ProjectStatus, project_tab_ui };
+use crate::components::sessions::{ session_tabs_ui, SessionAction };
 use crate::components::settings::settings_tab_ui;
+use crate::components::toasts::Toasts;
+use crate::components::transmit::transmit_bar_ui;
+use crate::components::triggers::{ trigger_status_ui, TriggerStatusAction };
+use crate::app::commands;
+use crate::app::session::{ Session, Shared };
 use crate::app::state::{ ApplicationState, Tab };
 use crate::app::parameters::gui; 
 use crate::models::banner::Banner;
+use crate::models::command::{ classify, parse_command, Input };
+use crate::models::command_registry::CommandRegistry;
+use crate::models::completion::complete;
+use crate::models::highlight::Highlighter;
+use crate::models::history::History;
+use crate::models::connection::ConnectionState;
+use crate::models::parsers::ParserSet;
+use crate::models::ports::{ list_ports, PortEntry, PortWatcher };
+use crate::models::profiles::ProfileDatabase;
+use crate::models::project_file::ProjectFile;
+use crate::models::scrollback::{ ViewMode, TIMESTAMP_MODES, VIEW_MODES };
+use crate::models::transport::Endpoint;
 
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 use egui_aesthetix::{
     themes::{
@@ -28,7 +52,7 @@ use egui_aesthetix::{
     Aesthetix,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct BackroundThread {
     pub tic: Arc<Mutex<String>>,
 }
@@ -43,16 +67,31 @@ impl BackroundThread {
 pub struct MyApp {
     pub tic_message: Arc<Mutex<String>>, // make a placeholder for the tic message
     pub tic_message_prev: String, // make a placeholder for the previous tic message
-    pub serial_port: Option<Arc<Mutex<Box<dyn serialport::SerialPort>>>>,
-    pub serial_port_found: bool,
-    pub serial_port_name: String,
+    /// The open sessions, each with its port and terminal
+    pub sessions: Vec<Session>,
+    /// Index of the session the tabs, commands and keys act on
+    pub active: usize,
+    /// Show every session's terminal side by side instead of the active one
+    side_by_side: bool,
+    pub port_watcher: PortWatcher,
+    pub port_error_prev: Option<String>,
+    pub profiles: ProfileDatabase,
+    /// Ports already seen by the auto-connect check, a device is considered once per plug-in
+    known_ports: Vec<String>,
     pub logger_text: Logger,
-    // wrap the scroller_text in an Arc to allow for multiple references
-    pub scroller_text: Arc<Mutex<String>>,
-    pub cursor_update: bool,
-    pub buffer_text: Arc<Mutex<String>>,
+    /// The parsers of the project, rebuilt when they are edited
+    pub parsers: ParserSet,
+    /// The highlight rules of the project, rebuilt when they are edited
+    pub highlighter: Highlighter,
+    toasts: Toasts,
+    /// The commands of the transmit bar
+    pub commands: Rc<CommandRegistry<MyApp>>,
     themes: Vec<Rc<dyn Aesthetix>>,
-    project: Project,
+    pub project: Project,
+    /// Lines sent from the transmit bars, one history for all sessions
+    pub history: History,
+    /// File the history was loaded from, it follows the project directory
+    history_path: Option<PathBuf>,
     state: ApplicationState,
     /// Tab labels and icons
     tab_labels: BTreeMap<Tab, &'static str>,
@@ -85,24 +124,43 @@ impl MyApp {
         let mut initial_display: Banner = Banner::new();
         initial_display.format();
 
-        let yy: Arc<Mutex<String>> = Arc::new(Mutex::new(initial_display.message.clone()));
-        let zz = yy.clone();
+        let (profiles, profile_errors) = ProfileDatabase::load();
+        for err in profile_errors {
+            initial_display.message += &format!("\nFailed to load device profiles: {}\n", err);
+        }
+
+        // refresh the port list as devices are plugged and unplugged
+        let port_watcher = PortWatcher::new();
+        let repaint_context = creation_context.egui_ctx.clone();
+        port_watcher.spawn(move || repaint_context.request_repaint());
+
+        let mut session = Session::new(1, &creation_context.egui_ctx);
+        session.scrollback.push_local(&initial_display.message);
 
         Self {
             tic_message: some_thread.tic.clone(), // clone here to get the value of the tic
             tic_message_prev: String::new(), // initialize the previous tic message
-            serial_port: None,
-            serial_port_found: false,
-            serial_port_name: String::new(),
+            sessions: vec![session],
+            active: 0,
+            side_by_side: false,
+            port_watcher,
+            port_error_prev: None,
+            profiles,
+            known_ports: Vec::new(),
             logger_text: Logger::default(),
-            scroller_text: zz,
-            buffer_text: Arc::new(Mutex::new(String::new())),
-            cursor_update: false,
             themes,
+            parsers: ParserSet::default(),
+            highlighter: Highlighter::default(),
+            toasts: Toasts::new(),
+            commands: Rc::new(commands::registry()),
             project: Project::new(),
+            history: History::new(),
+            history_path: None,
             state: ApplicationState::new(active_theme),
             tab_labels: [
                 (Tab::Home, "🏠  Home"),
+                (Tab::Connection, "🔌  Connection"),
+                (Tab::Plot, "📈  Plot"),
                 (Tab::Project, "⚙  Project"),
                 (Tab::Build, "📝  Build"),
                 (Tab::About, "ℹ  About"),
@@ -112,57 +170,511 @@ impl MyApp {
         }
     }
 
-    fn open_port(&mut self, port_name: &str, baud_rate: u32) -> Result<(), String> {
-        let serial_port_builder = serialport::new(port_name, baud_rate);
-        match serial_port_builder.open() {
-            Ok(serial_port) => {
-                self.serial_port = Some(Arc::new(Mutex::new(serial_port)));
-                Ok(())
+    /// The active session
+    pub fn session(&mut self) -> &mut Session {
+        &mut self.sessions[self.active]
+    }
+
+    /// The active session, with what the sessions share
+    fn session_shared(&mut self) -> (&mut Session, Shared<'_>) {
+        let shared = Shared {
+            project: &self.project,
+            parsers: &self.parsers,
+            toasts: &mut self.toasts,
+        };
+        (&mut self.sessions[self.active], shared)
+    }
+
+    /// Open `port_name`, a serial port or an endpoint URL, in the active session and start
+    /// displaying its data, with the current line settings changed by the ones the matching
+    /// device profile sets
+    pub fn connect(&mut self, port_name: &str) {
+        let endpoint = match Endpoint::parse(port_name) {
+            Ok(endpoint) => endpoint,
+            Err(err) => {
+                self.session().scrollback.push_local(&format!("\n\n{}\n", err));
+                return;
+            }
+        };
+        let port = self.port_watcher
+            .snapshot()
+            .into_iter()
+            .filter(|_| endpoint.is_serial())
+            .find(|port| port.port_name == endpoint.address);
+        let profile = port.and_then(|port| self.profiles.find(&port).cloned());
+        let session = &mut self.sessions[self.active];
+        if let Some(profile) = profile.as_ref() {
+            let changed = profile.line
+                .as_ref()
+                .is_some_and(|line| line.apply(&mut session.serial_config));
+            if changed {
+                session.scrollback.push_local(&format!(
+                    "\n\nProfile {} sets {}, line settings now {}\n",
+                    profile.name,
+                    profile.line.as_ref().map(|line| line.summary()).unwrap_or_default(),
+                    session.serial_config.summary()
+                ));
+            }
+        }
+        let decoder = profile.as_ref().and_then(|profile| profile.decoder.as_deref());
+        if let Some(mode) = decoder.and_then(ViewMode::from_name) {
+            session.scrollback.set_mode(mode);
+        }
+
+        match session.connection.connect(&endpoint, &session.serial_config) {
+            Ok(()) => {
+                session.transmit.bytes_sent = 0;
+                let device = match &profile {
+                    Some(profile) => format!(" ({})", profile.name),
+                    None => String::new(),
+                };
+                session.scrollback.push_local(&format!(
+                    "\n\nConnecting to {}{} ...\n",
+                    endpoint,
+                    device
+                ));
             }
-            Err(e) => Err(format!("Failed to open serial port: {}", e)),
+            Err(err) => {
+                session.scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+        }
+    }
+
+    /// Write `line` to the port of the active session, terminated by the selected line ending
+    pub fn send_line(&mut self, line: &str) {
+        let (session, shared) = self.session_shared();
+        session.send_line(line, &shared);
+    }
+
+    /// Write `bytes` to the port of the active session as they are
+    pub fn send_bytes(&mut self, bytes: Vec<u8>) {
+        let (session, shared) = self.session_shared();
+        session.send_bytes(bytes, &shared);
+    }
+
+    /// Runs a command typed in the transmit bar after a `/`, they are never sent to the port
+    fn run_command(&mut self, input: &str) {
+        let commands = Rc::clone(&self.commands);
+        let result = parse_command(input).and_then(|line| commands.run(self, &line));
+        if let Err(err) = result {
+            self.session().scrollback.push_local(&format!("\n\n/{}: {}\n", input.trim(), err));
         }
     }
 
-    /// Start reading from the serial port, if it is open, and one may want to do this to
-    /// display data in the UI text terminal. Of course, having this automatically start
-    /// when the application is started can be a reasonable default behavior for the user.
-    fn start_reading(&self) {
-        if let Some(serial_port) = &self.serial_port {
-            let serial_port_clone = Arc::clone(serial_port);
-            let text_buffer_clone = Arc::clone(&self.scroller_text);
-
-            task::spawn(async move {
-                let binding = serial_port_clone.lock().unwrap().as_mut().try_clone().unwrap();
-                let mut reader = BufReader::new(binding);
-                loop {
-                    let mut buffer = String::new();
-                    match reader.read_line(&mut buffer) {
-                        Ok(_) => {
-                            let mut data = text_buffer_clone.lock().unwrap();
-                            data.push_str(&buffer);
+    pub fn start_logging(&mut self) {
+        let (session, shared) = self.session_shared();
+        session.start_logging(&shared);
+    }
+
+    pub fn stop_logging(&mut self) {
+        let (session, shared) = self.session_shared();
+        session.stop_logging(&shared);
+    }
+
+    pub fn start_capture(&mut self) {
+        let (session, shared) = self.session_shared();
+        session.start_capture(&shared);
+    }
+
+    pub fn stop_capture(&mut self) {
+        self.session().stop_capture();
+    }
+
+    pub fn start_replay(&mut self) {
+        let (session, shared) = self.session_shared();
+        session.start_replay(&shared);
+    }
+
+    pub fn stop_replay(&mut self) {
+        self.session().stop_replay();
+    }
+
+    /// Opens a session after the others and makes it active
+    fn add_session(&mut self, ctx: &egui::Context) {
+        let id = self.sessions.iter().map(|session| session.id).max().unwrap_or(0) + 1;
+        let mut session = Session::new(id, ctx);
+        session.scrollback.push_local(&format!(
+            "Session {}, connect from the Connection tab or with /connect\n",
+            id
+        ));
+        self.sessions.push(session);
+        self.active = self.sessions.len() - 1;
+    }
+
+    /// Closes the session at `index`, finishing its files, the last session stays open
+    fn close_session(&mut self, index: usize) {
+        if self.sessions.len() == 1 {
+            return;
+        }
+        let shared = Shared {
+            project: &self.project,
+            parsers: &self.parsers,
+            toasts: &mut self.toasts,
+        };
+        let mut session = self.sessions.remove(index);
+        session.close(&shared);
+        if self.active > index || self.active == self.sessions.len() {
+            self.active -= 1;
+        }
+    }
+
+    /// Saves the Project tab settings and parsers to the project directory
+    fn save_project(&mut self) {
+        let path = match self.project.file_path() {
+            Ok(path) => path,
+            Err(err) => {
+                self.session().scrollback.push_local(&format!("\n\n{}\n", err));
+                return;
+            }
+        };
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        if self.project.created_at.trim().is_empty() {
+            self.project.created_at = now.clone();
+        }
+        self.project.updated_at = now;
+        match self.project.to_file().save(&path) {
+            Ok(()) => {
+                self.session().scrollback.push_local(&format!(
+                    "\n\nSaved project to {}\n",
+                    path.display()
+                ));
+            }
+            Err(err) => {
+                self.session().scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+        }
+    }
+
+    fn load_project(&mut self) {
+        let loaded = self.project
+            .file_path()
+            .and_then(|path| ProjectFile::load(&path).map(|file| (path, file)));
+        match loaded {
+            Ok((path, file)) => {
+                self.project.apply_file(file);
+                self.session().scrollback.push_local(&format!(
+                    "\n\nLoaded project from {}\n",
+                    path.display()
+                ));
+            }
+            Err(err) => {
+                self.session().scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+        }
+    }
+
+    /// Renders the terminal of the session at `session_index`, a click in it makes it the
+    /// active session
+    fn terminal_ui(&mut self, ui: &mut egui::Ui, session_index: usize) {
+        let id = self.sessions[session_index].id;
+        let sent = ui.push_id(id, |ui| {
+            let session = &mut self.sessions[session_index];
+
+            ui.horizontal(|ui| {
+                if
+                    ui
+                        .button(egui::RichText::new("Clear").color(egui::Color32::GREEN))
+                        .clicked()
+                {
+                    session.scrollback.clear();
+                }
+
+                if session.scrollback.is_paused() {
+                    let resume = ui
+                        .button(egui::RichText::new("Resume").color(egui::Color32::YELLOW))
+                        .on_hover_text(
+                            format!("{} reads held back", session.scrollback.held_chunks())
+                        );
+                    if resume.clicked() {
+                        session.scrollback.set_paused(false);
+                    }
+                } else {
+                    let pause = ui
+                        .button("Pause")
+                        .on_hover_text("Hold new data back, it is still logged");
+                    if pause.clicked() {
+                        session.scrollback.set_paused(true);
+                    }
+                }
+
+                if
+                    ui
+                        .button(egui::RichText::new("System Info").color(egui::Color32::GREEN))
+                        .clicked()
+                {
+                    session.scrollback.clear();
+                    session.scrollback.push_local(&self.logger_text.system_info());
+                }
+
+                let serial_port_button_widget = ui
+                    .button(egui::RichText::new("Find Serial Port").color(egui::Color32::GREEN))
+                    .on_hover_text("List the serial ports, connect from the Connection tab");
+
+                if serial_port_button_widget.clicked() {
+                    match list_ports() {
+                        Ok(ports) if ports.is_empty() => {
+                            session.scrollback.push_local("\n\nNo serial ports found\n");
                         }
-                        Err(e) => {
-                            eprintln!("Error reading from serial port: {:?}", e);
-                            break;
+                        Ok(ports) => {
+                            for port in ports.iter() {
+                                session.scrollback.push_local(&format!(
+                                    "\n\nFound a Serial Port:\n{}",
+                                    port.describe()
+                                ));
+                                if let Some(profile) = self.profiles.find(port) {
+                                    session.scrollback.push_local(&format!(
+                                        "\n** Device profile: {}\n",
+                                        profile.name
+                                    ));
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            session.scrollback.push_local(&format!("\n\n{}\n", err));
                         }
                     }
-                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+
+                let status_action = connection_status_ui(ui, &session.connection);
+                if let Some(ConnectionAction::Disconnect) = status_action {
+                    session.connection.disconnect();
+                }
+            }); // end horizontal
+
+            ui.horizontal(|ui| {
+                ui.label("View");
+                let mut mode = session.scrollback.mode();
+                for view_mode in VIEW_MODES {
+                    ui.selectable_value(&mut mode, view_mode, view_mode.label());
+                }
+                session.scrollback.set_mode(mode);
+                let mut ansi = session.scrollback.ansi();
+                ui.checkbox(&mut ansi, "ANSI").on_hover_text(
+                    "Follow colour and line editing escape sequences in text mode"
+                );
+                session.scrollback.set_ansi(ansi);
+                let mut cr_newline = session.scrollback.cr_newline();
+                ui.checkbox(&mut cr_newline, "CR ends lines").on_hover_text(
+                    "A carriage return not followed by a line feed ends the line, for devices \
+                    that send CR only"
+                );
+                session.set_cr_newline(cr_newline);
+
+                ui.separator();
+                ui.label("Timestamps");
+                let mut timestamps = session.scrollback.timestamps();
+                for timestamp_mode in TIMESTAMP_MODES {
+                    ui.selectable_value(
+                        &mut timestamps,
+                        timestamp_mode,
+                        timestamp_mode.label()
+                    );
+                }
+                session.scrollback.set_timestamps(timestamps);
+
+                ui.separator();
+                let mut max_lines = session.scrollback.max_lines();
+                ui.label("Lines");
+                ui.add(
+                    egui::DragValue
+                        ::new(&mut max_lines)
+                        .speed(1000)
+                        .range(1000..=10_000_000)
+                ).on_hover_text("Lines kept in the scrollback, older lines are dropped");
+                session.scrollback.set_max_lines(max_lines);
+
+                let mut spill = session.scrollback.spill_path().is_some();
+                let spill_checkbox = ui
+                    .checkbox(&mut spill, "Spill to disk")
+                    .on_hover_text("Append dropped lines to a file instead of discarding them");
+                if spill_checkbox.changed() {
+                    session.set_spill(spill);
+                }
+                if let Some(err) = session.scrollback.take_error() {
+                    session.scrollback.push_local(&format!("\n\n{}\n", err));
+                }
+
+                if ui.button("Copy").on_hover_text("Copy the scrollback").clicked() {
+                    ui.ctx().copy_text(session.scrollback.text());
                 }
             });
+
+            // ============================================================
+            // ** TERMINAL::VIEW **
+            // ============================================================
+            let shortcuts = session_index == self.active;
+            find_bar_ui(ui, &mut session.search, &session.scrollback, shortcuts);
+            if session.search.update(&session.scrollback) {
+                ui.ctx().request_repaint();
+            }
+
+            // only the rows in view are laid out, so a long capture stays responsive
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+            let row_count = session.search.row_count(&session.scrollback);
+            let max_height = ui.available_height() - 30.0;
+            let mut scroll_area = egui::ScrollArea
+                ::both()
+                .id_source("scrollerx")
+                .max_height(max_height)
+                .auto_shrink([false, false])
+                .stick_to_bottom(true);
+            if let Some(row) = session.search.scroll_to.take() {
+                // the selected line in the middle of the view
+                let row_span = row_height + ui.spacing().item_spacing.y;
+                let offset = (row as f32) * row_span - max_height / 2.0;
+                scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+            }
+            scroll_area
+                .show_rows(ui, row_height, row_count, |ui, rows| {
+                    for row in rows {
+                        let Some(index) = session.search.row_line(&session.scrollback, row) else {
+                            continue;
+                        };
+                        let line = session.scrollback.line(index).unwrap_or_default();
+                        let plain = self.highlighter.is_empty() && !session.search.is_active();
+                        if plain && !line.contains('\x1b') {
+                            let text = egui::RichText
+                                ::new(line)
+                                .monospace()
+                                .color(egui::Color32::GREEN);
+                            ui.add(egui::Label::new(text).extend());
+                            continue;
+                        }
+                        let number = session.scrollback.first_line() + index;
+                        let current = session.search.is_current(number);
+                        let job = styled_line(
+                            line,
+                            &self.highlighter,
+                            &session.search,
+                            current,
+                            &font_id,
+                            egui::Color32::GREEN
+                        );
+                        ui.add(egui::Label::new(job).extend());
+                    }
+                }); // end scroll area
+
+            // ============================================================
+            // ** TERMINAL::TRANSMIT **
+            // ============================================================
+            let connected = session.connection.is_connected();
+            let commands = Rc::clone(&self.commands);
+            let ports = &self.port_watcher;
+            transmit_bar_ui(ui, &mut session.transmit, &mut self.history, connected, |input| {
+                let ports: Vec<String> = ports
+                    .snapshot()
+                    .into_iter()
+                    .map(|port| port.port_name)
+                    .collect();
+                complete(&commands, input, &ports)
+            })
+        }).inner;
+        if ui.ui_contains_pointer() && ui.input(|i| i.pointer.primary_pressed()) {
+            self.active = session_index;
+        }
+
+        if let Some(line) = sent {
+            // commands act on the active session
+            self.active = session_index;
+            if let Err(err) = self.history.push(&line) {
+                self.session().scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+            match classify(&line) {
+                Input::Command(command) => self.run_command(command),
+                Input::Send(text) => self.send_line(text),
+            }
+        }
+    }
+
+    /// Loads the transmit history of the project once its directory exists or changes
+    fn follow_history(&mut self) {
+        let path = self.project.history_path();
+        if path == self.history_path {
+            return;
+        }
+        self.history_path = path.clone();
+        // a search found an entry of the history being replaced
+        for session in &mut self.sessions {
+            session.transmit.search = None;
         }
+        self.history = match path.map(|path| History::load(&path)) {
+            Some(Ok(history)) => history,
+            Some(Err(err)) => {
+                self.session().scrollback.push_local(&format!("\n\n{}\n", err));
+                History::new()
+            }
+            None => History::new(),
+        };
     }
 
-    /// Stop reading from the serial port, if it is open, and one may want to do this to
-    /// avoid continuous data being displayed in the UI text terminal.
-    fn _stop_reading(&self) {
-        if let Some(serial_port) = &self.serial_port {
-            drop(serial_port.lock().unwrap());
+    /// Connect to newly plugged devices whose profile asks for it, as long as the active
+    /// session has no port open
+    fn auto_connect(&mut self, ports: &[PortEntry]) {
+        let appeared: Vec<&PortEntry> = ports
+            .iter()
+            .filter(|port| !self.known_ports.contains(&port.port_name))
+            .collect();
+        let candidate = appeared
+            .into_iter()
+            .find(|port| self.profiles.find(port).is_some_and(|profile| profile.auto_connect))
+            .map(|port| port.port_name.clone());
+        self.known_ports = ports
+            .iter()
+            .map(|port| port.port_name.clone())
+            .collect();
+
+        if let Some(port_name) = candidate {
+            if self.session().connection.state == ConnectionState::Disconnected {
+                self.connect(&port_name);
+            }
         }
     }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.project.parsers != self.parsers.configs {
+            self.parsers = ParserSet::new(&self.project.parsers);
+        }
+        for session in &mut self.sessions {
+            if self.project.triggers != session.triggers.configs {
+                session.triggers.set_configs(&self.project.triggers);
+            }
+            let mut shared = Shared {
+                project: &self.project,
+                parsers: &self.parsers,
+                toasts: &mut self.toasts,
+            };
+            session.poll(ctx, &mut shared);
+        }
+        self.follow_history();
+        // trigger colours are drawn over the highlight rules
+        let triggers = &self.sessions[self.active].triggers;
+        let highlights = [&self.project.highlights[..], &triggers.highlights[..]].concat();
+        if highlights != self.highlighter.configs {
+            self.highlighter = Highlighter::new(&highlights);
+        }
+
+        // report enumeration errors from the port watcher once
+        let port_error = self.port_watcher.error.lock().unwrap().clone();
+        if port_error != self.port_error_prev {
+            if let Some(err) = &port_error {
+                self.session().scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+            self.port_error_prev = port_error;
+        }
+
+        // determine whether to print a tic message
+        let tic_message = self.tic_message.lock().unwrap().clone();
+        if tic_message != self.tic_message_prev {
+            self.session().scrollback.push_local(&format!("\n\n{}\n", tic_message));
+            self.tic_message_prev = tic_message;
+        }
+
+        let ports = self.port_watcher.snapshot();
+        self.auto_connect(&ports);
+
         // This builds the main side navigation panel
         egui::SidePanel
             ::left("main_side_panel")
@@ -222,11 +734,94 @@ impl eframe::App for MyApp {
                             .max_size(egui::Vec2 { x: 500.0, y: 400.0 })
                     );
                 });
+
+                let session = &mut self.sessions[self.active];
+                let scrollback = &session.scrollback;
+                let action = trigger_status_ui(
+                    ui,
+                    &session.triggers.counts,
+                    &session.bookmarks,
+                    |bookmark| {
+                        bookmark.line.is_some_and(|(generation, line)| {
+                            generation == scrollback.generation() &&
+                                line >= scrollback.first_line()
+                        })
+                    }
+                );
+                match action {
+                    Some(TriggerStatusAction::ResetCounts) => session.triggers.reset_counts(),
+                    Some(TriggerStatusAction::ClearBookmarks) => session.bookmarks.clear(),
+                    Some(TriggerStatusAction::Reveal(index)) => {
+                        if let Some((_, line)) = session.bookmarks[index].line {
+                            session.search.reveal(&session.scrollback, line);
+                        }
+                    }
+                    None => {}
+                }
             }
 
             // Display the project_tab_ui if the active tab is the project tab
             if self.state.active_tab == Tab::Project {
-                project_tab_ui(ctx, ui, &mut self.project);
+                let session = &self.sessions[self.active];
+                let status = ProjectStatus {
+                    session: session.id,
+                    data_log: session.data_log.as_ref(),
+                    capture: session.capture.as_ref(),
+                    replay: session.replay.as_ref(),
+                    parser_errors: &self.parsers.errors,
+                    highlight_errors: &self.highlighter.errors,
+                    trigger_errors: &session.triggers.errors,
+                };
+                match project_tab_ui(ctx, ui, &mut self.project, status) {
+                    Some(ProjectAction::StartLogging) => self.start_logging(),
+                    Some(ProjectAction::StopLogging) => self.stop_logging(),
+                    Some(ProjectAction::StartCapture) => self.start_capture(),
+                    Some(ProjectAction::StopCapture) => self.stop_capture(),
+                    Some(ProjectAction::StartReplay) => self.start_replay(),
+                    Some(ProjectAction::StopReplay) => self.stop_replay(),
+                    Some(ProjectAction::Save) => self.save_project(),
+                    Some(ProjectAction::Load) => self.load_project(),
+                    None => {}
+                }
+            }
+
+            if self.state.active_tab == Tab::Plot {
+                plot_tab_ui(ui, &mut self.sessions[self.active].plot);
+            }
+
+            if self.state.active_tab == Tab::Connection {
+                let session = &mut self.sessions[self.active];
+                let action = connection_tab_ui(
+                    ui,
+                    &mut session.serial_config,
+                    &mut session.network,
+                    &ports,
+                    &self.profiles,
+                    &session.connection
+                );
+                match action {
+                    Some(ConnectionAction::Connect(port_name)) => self.connect(&port_name),
+                    Some(ConnectionAction::Disconnect) => session.connection.disconnect(),
+                    Some(ConnectionAction::ApplyConfig) => {
+                        let config = &session.serial_config;
+                        let message = match session.connection.apply_config(config) {
+                            Ok(()) => format!("Line settings {}", config.summary()),
+                            Err(err) => err,
+                        };
+                        session.scrollback.push_local(&format!("\n\n{}\n", message));
+                    }
+                    Some(ConnectionAction::SetDtr(on)) => {
+                        if let Err(err) = session.connection.set_dtr(on) {
+                            session.scrollback.push_local(&format!("\n\n{}\n", err));
+                        }
+                    }
+                    Some(ConnectionAction::SetRts(on)) => {
+                        if let Err(err) = session.connection.set_rts(on) {
+                            session.scrollback.push_local(&format!("\n\n{}\n", err));
+                        }
+                    }
+                    None => {}
+                }
             }
 
             // project_tab_ui(ctx, ui, &mut self.project);
@@ -244,223 +839,34 @@ impl eframe::App for MyApp {
                 ui.heading("Terminal");
                 ui.separator();
 
-                // egui::Window::new("Logger Terminal")
-                //     .frame(logger_frame)
-                //     .show(ctx, |ui| {
-                //         ui.add_space( 0.0);
-                ui.horizontal(|ui| {
-                    if
-                        ui
-                            .button(egui::RichText::new("Clear").color(egui::Color32::GREEN))
-                            .clicked()
-                    {
-                        *self.scroller_text.lock().unwrap() = self.logger_text.clear();
-                    }
-
-                    if
-                        ui
-                            .button(egui::RichText::new("System Info").color(egui::Color32::GREEN))
-                            .clicked()
-                    {
-                        *self.scroller_text.lock().unwrap() = self.logger_text.system_info();
-                    }
-
-                    let serial_port_button_widget = ui
-                        .button(egui::RichText::new("Find Serial Port").color(egui::Color32::GREEN))
-                        .on_hover_text("Find a valid serial port");
-
-                    if serial_port_button_widget.clicked() {
-                        self.serial_port_found = false;
-                        let serial_port_list = serialport::available_ports().unwrap();
-                        for item in serial_port_list.iter() {
-                            // Determine if the serial port is a "ttyUSB" or "ttyACM" port
-                            // and if so, print it, otherwise ignore it
-
-                            if
-                                item.port_name.contains("ttyUSB") ||
-                                item.port_name.contains("ttyACM")
-                            {
-                                *self.scroller_text.lock().unwrap() += &format!(
-                                    "\n\nFound a valid Serial Port:\n"
-                                );
-                                *self.scroller_text.lock().unwrap() += &format!(
-                                    "{}\n",
-                                    item.port_name
-                                );
-                                // use SerialPortType to determine the manufacter and product
-
-                                match &item.port_type {
-                                    serialport::SerialPortType::UsbPort(usb_info) => {
-                                        *self.scroller_text.lock().unwrap() += &format!(
-                                            "Manufacturer  = {}\n",
-                                            usb_info.manufacturer.clone().unwrap()
-                                        );
-                                        *self.scroller_text.lock().unwrap() += &format!(
-                                            "Product       = {}\n",
-                                            usb_info.product.clone().unwrap()
-                                        );
-                                        *self.scroller_text.lock().unwrap() += &format!(
-                                            "Serial Number = {}\n",
-                                            usb_info.serial_number.clone().unwrap()
-                                        );
-                                        *self.scroller_text.lock().unwrap() += &format!(
-                                            "Vendor ID     = {}\n",
-                                            usb_info.vid.clone()
-                                        );
-                                        *self.scroller_text.lock().unwrap() += &format!(
-                                            "Product ID    = {}\n",
-                                            usb_info.pid.clone()
-                                        );
-                                        // determine if the manufacturer is "Digilent" and if so, print that it is AMD-Xilinx Digilent end point
-                                        if usb_info.manufacturer.clone().unwrap() == "Digilent" {
-                                            *self.scroller_text.lock().unwrap() += &format!(
-                                                "\n** This port is an AMD-Xilinx Digilent end point\n"
-                                            );
-                                            self.serial_port_found = true;
-                                            self.serial_port_name = item.port_name.clone();
-                                        }
-                                    }
-                                    serialport::SerialPortType::PciPort => todo!(),
-                                    serialport::SerialPortType::BluetoothPort => todo!(),
-                                    serialport::SerialPortType::Unknown => todo!(),
-                                }
-
-                                //*self.scroller_text.lock().unwrap() += &format!("{}\n", item.port_name);
-                            }
-                        }
-                    }
-
-                    if self.serial_port_found {
-                        // now read from the serial port, and put the data into the scroller_text
-                        // if the serial port is found
-                        let default_baud_rate = 460800;
-                        let serial_port_name = self.serial_port_name.clone();
-                        if let Err(err) = self.open_port(&serial_port_name, default_baud_rate) {
-                            ui.colored_label(egui::Color32::RED, egui::RichText::new(err));
-                            return;
-                        }
-                        self.start_reading();
-                    }
+                let labels: Vec<(String, bool)> = self.sessions
+                    .iter()
+                    .map(|session| (session.label(), session.connection.is_connected()))
+                    .collect();
+                let action = session_tabs_ui(
+                    ui,
+                    &labels,
+                    &mut self.active,
+                    &mut self.side_by_side
+                );
+                match action {
+                    Some(SessionAction::Add) => self.add_session(ctx),
+                    Some(SessionAction::Close(index)) => self.close_session(index),
+                    None => {}
+                }
 
-                    // determine whether to print a tic message
-                    if self.tic_message.lock().unwrap().clone() != self.tic_message_prev {
-                        *self.scroller_text.lock().unwrap() += &format!(
-                            "\n\n{}\n",
-                            self.tic_message.lock().unwrap()
-                        );
-                        self.tic_message_prev = self.tic_message.lock().unwrap().clone();
-                    }
-                }); // end horizontal
-
-                let alpha: &mut bool = &mut false;
-                // ============================================================
-                // ** TERMINAL::TEXT_EDIT **
-                // ============================================================
-                let mut _scroller = egui::ScrollArea
-                    ::vertical()
-                    .id_source("scrollerx")
-                    .stick_to_bottom(true)
-                    .show(ui, |ui| {
-                        let output = egui::TextEdit
-                            ::multiline(&mut *self.scroller_text.lock().unwrap())
-                            .id(egui::Id::new("terminal"))
-                            .text_color(egui::Color32::GREEN)
-                            .font(egui::TextStyle::Monospace) // for cursor height
-                            .interactive(true)
-                            .desired_rows(20)
-                            .lock_focus(true)
-                            .desired_width(550.0)
-                            .show(ui);
-
-                        // if terminal text is changed and the Enter key was pressed, echo scroller text to stdout
-                        if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                            let mut scroller_bytes = self.scroller_text
-                                .lock()
-                                .unwrap()
-                                .as_bytes()
-                                .to_vec();
-                            let mut j = scroller_bytes.len();
-                            let mut scroller_text = String::new();
-
-                            while j > 0 {
-                                match scroller_bytes[j - 1] {
-                                    10 => {
-                                        scroller_bytes.truncate(scroller_bytes.len() - 1);
-                                    }
-                                    _ => {
-                                        println!("\n Buffer processed ...");
-                                        // now print the scroller_bytes as a String
-                                        println!("Truncated to length: {}", scroller_bytes.len());
-                                        scroller_text = String::from_utf8(
-                                            scroller_bytes.clone()
-                                        ).unwrap();
-                                        println!("Scroller Text = {}", scroller_text);
-                                        break;
-                                    }
-                                }
-                                j = j - 1;
-                            }
-                            *self.buffer_text.lock().unwrap() = scroller_text.clone();
-                            println!("Buffer Text = {}", self.buffer_text.lock().unwrap());
-                            // check to see if the last characters of scroller_text are equal to "clear"
-                            // using a regex to match the last 5 characters of the scroller_text
-                            // if the last 5 characters are "clear", then clear the scroller_text
-                            // if the last 5 characters are "exit", then exit the program
-
-                            let re_clear = regex::Regex::new(r"clear").unwrap();
-                            //let re_exit = regex::Regex::new(r"exit").unwrap();
-                            let re_version = regex::Regex::new(r"version").unwrap();
-                            let re_help = regex::Regex::new(r"help").unwrap();
-                            let re_system = regex::Regex::new(r"system").unwrap();
-
-                            if re_clear.is_match(&scroller_text) {
-                                *self.scroller_text.lock().unwrap() = self.logger_text.clear();
-                                self.cursor_update = true;
-                            } else if re_system.is_match(&scroller_text) {
-                                *self.scroller_text.lock().unwrap() =
-                                    self.logger_text.system_info();
-                                self.cursor_update = true;
-                            } else if re_version.is_match(&scroller_text) {
-                                // clear the logger first
-                                *self.scroller_text.lock().unwrap() = self.logger_text.clear();
-                                let mut initial_display = Banner::new();
-                                initial_display.format();
-                                *self.scroller_text.lock().unwrap() += &initial_display.message;
-                                self.cursor_update = true;
-                            } else if re_help.is_match(&scroller_text) {
-                                *self.scroller_text.lock().unwrap() = "Commands:\n".to_string();
-                                *self.scroller_text.lock().unwrap() +=
-                                    "clear   - clear the terminal";
-                                *self.scroller_text.lock().unwrap() +=
-                                    "\nversion - print the version information for Vescript";
-                                *self.scroller_text.lock().unwrap() +=
-                                    "\nsystem  - print the OS system info for host machine\n";
-                                *self.scroller_text.lock().unwrap() += "exit \n";
-                                self.cursor_update = true;
-                            } else {
-                            }
-                        } // end if ui.input
-
-                        if self.cursor_update {
-                            if
-                                let Some(mut state) = egui::TextEdit::load_state(
-                                    ui.ctx(),
-                                    output.response.id
-                                )
-                            {
-                                let ccursor = egui::text::CCursor::new(
-                                    self.scroller_text.lock().unwrap().chars().count()
-                                );
-                                state.cursor.set_char_range(
-                                    Some(egui::text::CCursorRange::one(ccursor))
-                                );
-                                state.store(ui.ctx(), output.response.id);
-                                ui.ctx().memory_mut(|mem| mem.request_focus(output.response.id)); // give focus back to the [`TextEdit`].
-                            }
-                            self.cursor_update = false;
+                if self.side_by_side && self.sessions.len() > 1 {
+                    ui.columns(self.sessions.len(), |columns| {
+                        for (index, column) in columns.iter_mut().enumerate() {
+                            self.terminal_ui(column, index);
                         }
-                    }); // end scroll area
+                    });
+                } else {
+                    self.terminal_ui(ui, self.active);
+                }
             }); // end vertical
         });
+
+        self.toasts.show(ctx);
     }
 }
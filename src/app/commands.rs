@@ -0,0 +1,235 @@
+//! The terminal commands, typed after `/` in the transmit bar. A new command only needs an
+//! entry in `registry`, `/help` lists it from its declaration.
+
+use crate::app::app::MyApp;
+use crate::models::banner::Banner;
+use crate::models::command::CommandLine;
+use crate::models::command_registry::{ ArgKind, ArgSpec, Command, CommandRegistry, OptionSpec };
+use crate::models::scrollback::ViewMode;
+
+const START_STOP: &[&str] = &["start", "stop"];
+const PLOT_ACTIONS: &[&str] = &["pause", "resume", "clear"];
+const VIEW_MODES: &[&str] = &["text", "hex", "mixed"];
+
+pub fn registry() -> CommandRegistry<MyApp> {
+    let mut registry = CommandRegistry::new();
+    registry.register(Command {
+        name: "help",
+        aliases: &["?"],
+        args: vec![ArgSpec::optional("command", ArgKind::Command, "Command to describe")],
+        options: Vec::new(),
+        description: "List the commands, or describe one",
+        run: help,
+    });
+    registry.register(Command {
+        name: "clear",
+        aliases: &["cls"],
+        args: Vec::new(),
+        options: Vec::new(),
+        description: "Clear the terminal",
+        run: |app, _| {
+            app.session().scrollback.clear();
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "version",
+        aliases: &[],
+        args: Vec::new(),
+        options: Vec::new(),
+        description: "Print the version information",
+        run: |app, _| {
+            app.session().scrollback.clear();
+            let mut initial_display = Banner::new();
+            initial_display.format();
+            app.session().scrollback.push_local(&initial_display.message);
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "system",
+        aliases: &[],
+        args: Vec::new(),
+        options: Vec::new(),
+        description: "Print the OS system info for the host machine",
+        run: |app, _| {
+            app.session().scrollback.clear();
+            let info = app.logger_text.system_info();
+            app.session().scrollback.push_local(&info);
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "connect",
+        aliases: &["open"],
+        args: vec![ArgSpec::required("port", ArgKind::Port, "Port or URL, i.e. tcp://host:2000")],
+        options: Vec::new(),
+        description: "Open a port with the current line settings or its device profile",
+        run: |app, line| {
+            if app.session().connection.active_port().is_some() {
+                return Err("Already connected, /disconnect first".to_string());
+            }
+            app.connect(&line.args[0].text());
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "disconnect",
+        aliases: &["close"],
+        args: Vec::new(),
+        options: Vec::new(),
+        description: "Close the port",
+        run: |app, _| {
+            app.session().connection.disconnect();
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "baud",
+        aliases: &[],
+        args: vec![ArgSpec::required("rate", ArgKind::Integer, "Baud rate, i.e. 115200")],
+        options: Vec::new(),
+        description: "Set the baud rate, of the open port too",
+        run: baud,
+    });
+    registry.register(Command {
+        name: "send",
+        aliases: &[],
+        args: vec![ArgSpec::rest("text", "Text to send, quote it to keep spaces and escapes")],
+        options: vec![
+            OptionSpec {
+                name: "raw",
+                takes_value: false,
+                description: "Without the line ending",
+            },
+        ],
+        description: "Send text to the port",
+        run: send,
+    });
+    registry.register(Command {
+        name: "log",
+        aliases: &[],
+        args: vec![ArgSpec::required("action", ArgKind::Choice(START_STOP), "")],
+        options: Vec::new(),
+        description: "Start or stop the CSV data log of the Project tab",
+        run: |app, line| {
+            match line.args[0].text().as_str() {
+                "start" if app.session().data_log.is_some() => {
+                    return Err("Already logging".to_string());
+                }
+                "start" => app.start_logging(),
+                _ => app.stop_logging(),
+            }
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "capture",
+        aliases: &[],
+        args: vec![ArgSpec::required("action", ArgKind::Choice(START_STOP), "")],
+        options: Vec::new(),
+        description: "Start or stop the raw capture of the Project tab",
+        run: |app, line| {
+            match line.args[0].text().as_str() {
+                "start" if app.session().capture.is_some() => {
+                    return Err("Already capturing".to_string());
+                }
+                "start" => app.start_capture(),
+                _ => app.stop_capture(),
+            }
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "replay",
+        aliases: &[],
+        args: vec![ArgSpec::optional("file", ArgKind::Path, "Stops the replay without it")],
+        options: Vec::new(),
+        description: "Replay a capture at the speed set in the Project tab",
+        run: |app, line| {
+            app.stop_replay();
+            if let Some(file) = line.args.first() {
+                app.project.replay_path = file.text();
+                app.start_replay();
+            }
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "plot",
+        aliases: &[],
+        args: vec![ArgSpec::required("action", ArgKind::Choice(PLOT_ACTIONS), "")],
+        options: Vec::new(),
+        description: "Pause, resume or clear the Plot tab",
+        run: |app, line| {
+            match line.args[0].text().as_str() {
+                "pause" => app.session().plot.set_paused(true),
+                "resume" => app.session().plot.set_paused(false),
+                _ => app.session().plot.clear(),
+            }
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "view",
+        aliases: &[],
+        args: vec![ArgSpec::required("mode", ArgKind::Choice(VIEW_MODES), "")],
+        options: Vec::new(),
+        description: "Show the data as text, hex or both",
+        run: |app, line| {
+            let mode = ViewMode::from_name(&line.args[0].text()).ok_or("Unknown view mode")?;
+            app.session().scrollback.set_mode(mode);
+            Ok(())
+        },
+    });
+    registry
+}
+
+fn help(app: &mut MyApp, line: &CommandLine) -> Result<(), String> {
+    let commands = app.commands.clone();
+    let help = match line.args.first() {
+        Some(name) => {
+            let name = name.text();
+            let name = name.trim_start_matches('/');
+            let command = commands
+                .find(name)
+                .ok_or_else(|| format!("Unknown command '{}', try /help", name))?;
+            command.help()
+        }
+        None => commands.help(),
+    };
+    app.session().scrollback.push_local(&format!("\n\n{}", help));
+    Ok(())
+}
+
+fn baud(app: &mut MyApp, line: &CommandLine) -> Result<(), String> {
+    let rate = line.args[0]
+        .as_integer()
+        .and_then(|rate| u32::try_from(rate).ok())
+        .filter(|rate| *rate > 0)
+        .ok_or_else(|| format!("Invalid baud rate {}", line.args[0].text()))?;
+    let session = app.session();
+    session.serial_config.baud_rate = rate;
+    session.connection.apply_config(&session.serial_config)?;
+    session.scrollback.push_local(&format!("\n\nBaud rate {}\n", rate));
+    Ok(())
+}
+
+fn send(app: &mut MyApp, line: &CommandLine) -> Result<(), String> {
+    if !app.session().connection.is_connected() {
+        return Err("Not connected".to_string());
+    }
+    let mut bytes = Vec::new();
+    for (index, arg) in line.args.iter().enumerate() {
+        if index > 0 {
+            bytes.push(b' ');
+        }
+        bytes.extend(arg.bytes());
+    }
+    if !line.flag("raw") {
+        bytes = app.session().transmit.frame(&bytes);
+    }
+    app.send_bytes(bytes);
+    Ok(())
+}
+
@@ -1,3 +1,6 @@
 pub mod parameters; 
+#[allow(clippy::module_inception)]
 pub mod app; 
+pub mod commands;
+pub mod session;
 pub mod state; 
\ No newline at end of file
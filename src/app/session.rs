@@ -0,0 +1,392 @@
+//! A session, one port with its own line settings, terminal, log, capture and decoders. Several
+//! sessions are open at once, each in its own tab or pane.
+
+use eframe::egui;
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::components::project::Project;
+use crate::components::toasts::Toasts;
+use crate::models::capture::{ CaptureWriter, Replay };
+use crate::models::connection::{ Connection, Received };
+use crate::models::data_log::DataLog;
+use crate::models::parsers::ParserSet;
+use crate::models::plot::PlotData;
+use crate::models::scrollback::{ Chunk, Direction, Scrollback, Spill };
+use crate::models::search::Search;
+use crate::models::serial_config::SerialConfig;
+use crate::models::transmit::Transmit;
+use crate::models::transport::{ Endpoint, TransportKind };
+use crate::models::trigger::{ Action, Bookmark, Fired, Triggers };
+
+/// Bookmarks kept, the oldest are dropped
+const MAX_BOOKMARKS: usize = 1000;
+
+/// What the sessions share, lent by the application for a call
+pub struct Shared<'a> {
+    pub project: &'a Project,
+    pub parsers: &'a ParserSet,
+    pub toasts: &'a mut Toasts,
+}
+
+pub struct Session {
+    /// Number of the session, shown on its tab and added to the names of its files
+    pub id: usize,
+    pub connection: Connection,
+    /// Line settings from the Connection tab, used for the next connection
+    pub serial_config: SerialConfig,
+    /// Network endpoint typed in the Connection tab
+    pub network: Endpoint,
+    /// Everything shown in the terminal
+    pub scrollback: Scrollback,
+    /// Find bar of the terminal, and the lines it filters
+    pub search: Search,
+    pub transmit: Transmit,
+    /// CSV log of the lines received and sent, while logging
+    pub data_log: Option<DataLog>,
+    /// Raw capture of the session, while capturing
+    pub capture: Option<CaptureWriter>,
+    /// Capture being fed into the terminal
+    pub replay: Option<Replay>,
+    /// Values charted in the Plot tab
+    pub plot: PlotData,
+    /// The triggers of the project, rebuilt when they are edited
+    pub triggers: Triggers,
+    /// Lines kept by bookmarking triggers, oldest first
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl Session {
+    pub fn new(id: usize, ctx: &egui::Context) -> Session {
+        let repaint_context = ctx.clone();
+        Session {
+            id,
+            connection: Connection::new(move || repaint_context.request_repaint()),
+            serial_config: SerialConfig::new(),
+            network: Endpoint {
+                kind: TransportKind::TcpClient,
+                address: "localhost:2000".to_string(),
+                local_port: 0,
+            },
+            scrollback: Scrollback::new(),
+            search: Search::new(),
+            transmit: Transmit::new(),
+            data_log: None,
+            capture: None,
+            replay: None,
+            plot: PlotData::new(),
+            triggers: Triggers::new(),
+            bookmarks: Vec::new(),
+        }
+    }
+
+    /// Tab label, the number of the session and its port
+    pub fn label(&self) -> String {
+        match self.connection.active_port() {
+            Some(port) => format!("{} {}", self.id, port),
+            None => format!("{} Not connected", self.id),
+        }
+    }
+
+    /// Handles what arrived from the port and the replay since the last frame
+    pub fn poll(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        for received in self.connection.poll() {
+            match received {
+                Received::Data(chunk) => self.receive(chunk, true, shared),
+                Received::Connected(at) => self.scrollback.mark_connected(at),
+                Received::Status(message) => {
+                    self.scrollback.push_local(&format!("\n\n{}\n", message));
+                }
+            }
+        }
+        self.poll_replay(ctx, shared);
+    }
+
+    /// Write `line` to the open port, terminated by the selected line ending
+    pub fn send_line(&mut self, line: &str, shared: &Shared) {
+        let bytes = self.transmit.frame(line.as_bytes());
+        self.send_bytes(bytes, shared);
+    }
+
+    /// Write `bytes` to the open port as they are
+    pub fn send_bytes(&mut self, bytes: Vec<u8>, shared: &Shared) {
+        let Some(serial_port) = self.connection.port() else {
+            return;
+        };
+        let result = serial_port.lock().unwrap().write_all(&bytes);
+        match result {
+            Ok(()) => {
+                self.transmit.bytes_sent += bytes.len();
+                let chunk = Chunk::new(Direction::Tx, bytes);
+                self.record(&chunk, shared);
+                if self.transmit.local_echo {
+                    self.scrollback.push(chunk);
+                }
+            }
+            Err(e) => {
+                self.scrollback.push_local(&format!(
+                    "\n\nFailed to write to {}: {}\n",
+                    self.connection.port_name,
+                    e
+                ));
+            }
+        }
+    }
+
+    /// Ends received and sent lines at carriage returns not followed by a line feed, in the
+    /// terminal, the plot, the triggers and the data log
+    pub fn set_cr_newline(&mut self, cr_newline: bool) {
+        if cr_newline == self.scrollback.cr_newline() {
+            return;
+        }
+        self.scrollback.set_cr_newline(cr_newline);
+        self.plot.set_cr_newline(cr_newline);
+        self.triggers.set_cr_newline(cr_newline);
+        if let Some(log) = self.data_log.as_mut() {
+            log.set_cr_newline(cr_newline);
+        }
+    }
+
+    /// Starts the CSV data log at the path set in the Project tab
+    pub fn start_logging(&mut self, shared: &Shared) {
+        let result = shared.project
+            .log_path(self.id)
+            .and_then(|path| DataLog::create(&path, &shared.project.log_header()));
+        match result {
+            Ok(mut log) => {
+                log.set_cr_newline(self.scrollback.cr_newline());
+                self.scrollback.push_local(&format!(
+                    "\n\nLogging to {}\n",
+                    log.path.display()
+                ));
+                self.data_log = Some(log);
+            }
+            Err(err) => {
+                self.scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+        }
+    }
+
+    pub fn stop_logging(&mut self, shared: &Shared) {
+        let Some(log) = self.data_log.take() else {
+            return;
+        };
+        let lines = log.lines_written;
+        match log.finish(shared.parsers) {
+            Ok(path) => {
+                self.scrollback.push_local(&format!(
+                    "\n\nStopped logging, {} lines written to {}\n",
+                    lines,
+                    path.display()
+                ));
+            }
+            Err(err) => {
+                self.scrollback.push_local(&format!("\n\nFailed to finish log: {}\n", err));
+            }
+        }
+    }
+
+    /// Adds `chunk` to the data log and the capture, whichever is running, a failing one is
+    /// stopped
+    fn record(&mut self, chunk: &Chunk, shared: &Shared) {
+        if let Some(log) = self.data_log.as_mut() {
+            if let Err(err) = log.write(chunk, shared.parsers) {
+                let path = log.path.display().to_string();
+                self.data_log = None;
+                self.scrollback.push_local(&format!(
+                    "\n\nStopped logging to {}: {}\n",
+                    path,
+                    err
+                ));
+            }
+        }
+        if let Some(capture) = self.capture.as_mut() {
+            if let Err(err) = capture.write(chunk) {
+                let path = capture.path.display().to_string();
+                self.capture = None;
+                self.scrollback.push_local(&format!(
+                    "\n\nStopped capturing to {}: {}\n",
+                    path,
+                    err
+                ));
+            }
+        }
+    }
+
+    /// Handles bytes from the port, or from a replayed capture when not `live`. A replay is
+    /// not logged or captured again.
+    fn receive(&mut self, chunk: Chunk, live: bool, shared: &mut Shared) {
+        if live {
+            self.record(&chunk, shared);
+        }
+        self.plot.push(&chunk, shared.parsers);
+        let fired = self.triggers.push(&chunk);
+        self.scrollback.push(chunk);
+        for fired in fired {
+            self.fire(fired, live, shared);
+        }
+    }
+
+    /// Performs the actions of a trigger, after the line that fired it is in the terminal.
+    /// Replayed data does not answer the port or start and stop the data log.
+    fn fire(&mut self, fired: Fired, live: bool, shared: &mut Shared) {
+        for action in &fired.actions {
+            match action {
+                Action::StartLogging | Action::StopLogging | Action::Send(_) if !live => {}
+                Action::Bookmark => {
+                    // the line is not in the view while it is paused
+                    let last = (self.scrollback.first_line() + self.scrollback.complete_lines())
+                        .checked_sub(fired.lines_after + 1)
+                        .filter(|_| !self.scrollback.is_paused());
+                    if self.bookmarks.len() == MAX_BOOKMARKS {
+                        self.bookmarks.remove(0);
+                    }
+                    self.bookmarks.push(Bookmark {
+                        name: fired.name.clone(),
+                        at: fired.at,
+                        text: fired.text.clone(),
+                        line: last.map(|line| (self.scrollback.generation(), line)),
+                    });
+                }
+                Action::Toast => {
+                    shared.toasts.push(format!("{} #{}: {}", fired.name, self.id, fired.text));
+                }
+                Action::StartLogging if self.data_log.is_none() => self.start_logging(shared),
+                Action::StartLogging => {}
+                Action::StopLogging => self.stop_logging(shared),
+                Action::Send(response) if self.connection.is_connected() => {
+                    self.send_line(response, shared);
+                }
+                Action::Send(_) => {}
+                Action::Pause => self.scrollback.set_paused(true),
+            }
+        }
+    }
+
+    /// Starts the raw capture next to the data log
+    pub fn start_capture(&mut self, shared: &Shared) {
+        match shared.project.capture_path(self.id).and_then(|path| CaptureWriter::create(&path)) {
+            Ok(capture) => {
+                self.scrollback.push_local(&format!(
+                    "\n\nCapturing to {}\n",
+                    capture.path.display()
+                ));
+                self.capture = Some(capture);
+            }
+            Err(err) => {
+                self.scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+        }
+    }
+
+    pub fn stop_capture(&mut self) {
+        let Some(capture) = self.capture.take() else {
+            return;
+        };
+        let bytes = capture.bytes_written;
+        match capture.finish() {
+            Ok(path) => {
+                self.scrollback.push_local(&format!(
+                    "\n\nStopped capture, {} bytes written to {}\n",
+                    bytes,
+                    path.display()
+                ));
+            }
+            Err(err) => {
+                self.scrollback.push_local(&format!("\n\nFailed to finish capture: {}\n", err));
+            }
+        }
+    }
+
+    /// Starts feeding the capture selected in the Project tab into the terminal
+    pub fn start_replay(&mut self, shared: &Shared) {
+        let path = PathBuf::from(shared.project.replay_path.trim());
+        match Replay::open(&path, shared.project.replay_speed) {
+            Ok(replay) => {
+                let (_, total) = replay.progress();
+                self.scrollback.push_local(&format!(
+                    "\n\nReplaying {} chunks from {} at {} speed\n",
+                    total,
+                    path.display(),
+                    shared.project.replay_speed.label()
+                ));
+                if let Some(origin) = replay.origin() {
+                    self.scrollback.mark_connected(origin);
+                }
+                self.replay = Some(replay);
+            }
+            Err(err) => {
+                self.scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+        }
+    }
+
+    pub fn stop_replay(&mut self) {
+        if let Some(replay) = self.replay.take() {
+            let (done, total) = replay.progress();
+            self.scrollback.push_local(&format!(
+                "\n\nReplay of {} stopped after {} of {} chunks\n",
+                replay.path.display(),
+                done,
+                total
+            ));
+        }
+    }
+
+    /// Feeds the replayed chunks that are due and schedules the next frame for the rest
+    fn poll_replay(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        let now = std::time::Instant::now();
+        let chunks = replay.poll(now);
+        let next_in = replay.next_in(now);
+        for chunk in chunks {
+            self.receive(chunk, false, shared);
+        }
+        match next_in {
+            Some(wait) => ctx.request_repaint_after(wait),
+            None => {
+                if let Some(replay) = self.replay.take() {
+                    self.scrollback.push_local(&format!(
+                        "\n\nReplay of {} finished\n",
+                        replay.path.display()
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Starts or stops spilling the lines dropped from the scrollback to a file
+    pub fn set_spill(&mut self, enabled: bool) {
+        if !enabled {
+            self.scrollback.stop_spill();
+            return;
+        }
+        let Some(dir) = Spill::default_dir() else {
+            self.scrollback.push_local("\n\nNo local data directory to spill scrollback to\n");
+            return;
+        };
+        match self.scrollback.spill_to(&dir) {
+            Ok(path) => {
+                self.scrollback.push_local(&format!(
+                    "\n\nSpilling old scrollback lines to {}\n",
+                    path.display()
+                ));
+            }
+            Err(err) => {
+                self.scrollback.push_local(&format!("\n\n{}\n", err));
+            }
+        }
+    }
+
+    /// Finishes the files of the session and closes its port
+    pub fn close(&mut self, shared: &Shared) {
+        self.stop_replay();
+        self.stop_logging(shared);
+        self.stop_capture();
+        self.scrollback.stop_spill();
+        self.connection.disconnect();
+    }
+}
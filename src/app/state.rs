@@ -8,6 +8,10 @@ use std::rc::Rc;
 pub enum Tab {
     /// The home tab
     Home,
+    /// The serial connection tab
+    Connection,
+    /// The live plot tab
+    Plot,
     /// The settings tab
     Project, 
     /// The logs tab
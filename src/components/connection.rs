@@ -0,0 +1,311 @@
+//! Connection Tab, port picker, network endpoint and the serial line settings used when
+//! opening a port
+
+use eframe::egui;
+
+use crate::models::connection::{ Connection, ConnectionState };
+use crate::models::ports::PortEntry;
+use crate::models::profiles::ProfileDatabase;
+use crate::models::serial_config::{
+    SerialConfig,
+    DATA_BITS,
+    FLOW_CONTROLS,
+    PARITIES,
+    STANDARD_BAUD_RATES,
+    STOP_BITS,
+};
+use crate::models::transport::{ Endpoint, TransportKind, NETWORK_KINDS };
+
+pub const TEXT_SIZE: f32 = 12.0;
+
+/// What the user asked for in the Connection tab
+pub enum ConnectionAction {
+    Connect(String),
+    Disconnect,
+    /// Send the line settings of the panel to the open port
+    ApplyConfig,
+    SetDtr(bool),
+    SetRts(bool),
+}
+
+fn grid_label(ui_grid: &mut egui::Ui, text: &str) {
+    ui_grid.add(egui::Label::new(egui::RichText::new(text).size(TEXT_SIZE).monospace()));
+}
+
+/// Renders the connection status, the port list, the network endpoint and the serial line
+/// configuration panel
+pub fn connection_tab_ui(
+    ui_root: &mut egui::Ui,
+    config: &mut SerialConfig,
+    network: &mut Endpoint,
+    ports: &[PortEntry],
+    profiles: &ProfileDatabase,
+    connection: &Connection
+) -> Option<ConnectionAction> {
+    let mut action = None;
+    let idle = connection.state == ConnectionState::Disconnected;
+
+    ui_root.add_space(20.0);
+
+    ui_root.horizontal(|ui_row| {
+        if let Some(action_row) = connection_status_ui(ui_row, connection) {
+            action = Some(action_row);
+        }
+    });
+
+    if connection.is_connected() && connection.endpoint.has_line_settings() {
+        ui_root.horizontal(|ui_row| {
+            let mut dtr = connection.dtr;
+            if ui_row.checkbox(&mut dtr, "DTR").changed() {
+                action = Some(ConnectionAction::SetDtr(dtr));
+            }
+            let mut rts = connection.rts;
+            if ui_row.checkbox(&mut rts, "RTS").changed() {
+                action = Some(ConnectionAction::SetRts(rts));
+            }
+            let apply = ui_row
+                .add_enabled(*config != connection.config, egui::Button::new("Apply"))
+                .on_hover_text("Change the line settings of the open port to the ones below");
+            if apply.clicked() {
+                action = Some(ConnectionAction::ApplyConfig);
+            }
+        });
+    }
+
+    ui_root.add_space(10.0);
+
+    if ports.is_empty() {
+        ui_root.label("No serial ports found, plug in a device and it will show up here");
+    } else {
+        egui::Grid
+            ::new("connection_port_grid")
+            .striped(true)
+            .num_columns(8)
+            .show(ui_root, |ui_grid| {
+                for heading in ["Port", "Device", "Type", "VID", "PID", "Serial", "Product", ""] {
+                    ui_grid.label(egui::RichText::new(heading).size(TEXT_SIZE).strong());
+                }
+                ui_grid.end_row();
+
+                for port in ports {
+                    let is_open = connection.active_port() == Some(port.port_name.as_str());
+                    ui_grid.label(egui::RichText::new(&port.port_name).monospace());
+                    match profiles.find(port) {
+                        Some(profile) => {
+                            let mut hover = match &profile.line {
+                                Some(line) => line.summary(),
+                                None => "Current line settings".to_string(),
+                            };
+                            if let Some(decoder) = &profile.decoder {
+                                hover += &format!("\nDecoder: {}", decoder);
+                            }
+                            if profile.auto_connect {
+                                hover += "\nConnects automatically";
+                            }
+                            ui_grid
+                                .label(egui::RichText::new(&profile.name).strong())
+                                .on_hover_text(hover);
+                        }
+                        None => {
+                            ui_grid.label("-");
+                        }
+                    }
+                    ui_grid.label(&port.kind);
+                    ui_grid.label(egui::RichText::new(port.vid_text()).monospace());
+                    ui_grid.label(egui::RichText::new(port.pid_text()).monospace());
+                    ui_grid.label(port.serial_number.as_deref().unwrap_or("-"));
+                    ui_grid.label(port.product.as_deref().unwrap_or("-"));
+                    if is_open {
+                        ui_grid.label(
+                            egui::RichText::new(format!("● {}", connection.state.label())).color(
+                                egui::Color32::GREEN
+                            )
+                        );
+                    } else if ui_grid.add_enabled(idle, egui::Button::new("Connect")).clicked() {
+                        action = Some(ConnectionAction::Connect(port.port_name.clone()));
+                    }
+                    ui_grid.end_row();
+                }
+            });
+    }
+
+    ui_root.add_space(10.0);
+
+    ui_root.horizontal(|ui_row| {
+        ui_row.label(egui::RichText::new("Network").size(TEXT_SIZE).strong());
+        egui::ComboBox
+            ::from_id_source("connection_network_combo_box")
+            .width(100.0)
+            .selected_text(network.kind.label())
+            .show_ui(ui_row, |ui_combobox| {
+                for kind in NETWORK_KINDS {
+                    ui_combobox.selectable_value(&mut network.kind, kind, kind.label());
+                }
+            });
+        ui_row.add(
+            egui::TextEdit
+                ::singleline(&mut network.address)
+                .hint_text(network.kind.address_hint())
+                .font(egui::TextStyle::Monospace)
+                .desired_width(200.0)
+        );
+        if network.kind == TransportKind::Udp {
+            ui_row
+                .add(egui::DragValue::new(&mut network.local_port).prefix("local "))
+                .on_hover_text("Port to receive on, 0 for any");
+        }
+        let url = network.to_string();
+        if connection.active_port() == Some(url.as_str()) {
+            ui_row.label(
+                egui::RichText::new(format!("● {}", connection.state.label())).color(
+                    egui::Color32::GREEN
+                )
+            );
+        } else {
+            let enabled = idle && !network.address.trim().is_empty();
+            let connect = ui_row
+                .add_enabled(enabled, egui::Button::new("Connect"))
+                .on_hover_text(&url);
+            if connect.clicked() {
+                action = Some(ConnectionAction::Connect(url));
+            }
+        }
+    });
+
+    ui_root.add_space(20.0);
+
+    egui::Grid
+        ::new("connection_grid")
+        .striped(true)
+        .num_columns(2)
+        .show(ui_root, |ui_grid| {
+            grid_label(ui_grid, "Baud Rate");
+            ui_grid.horizontal(|ui_row| {
+                let selected = if STANDARD_BAUD_RATES.contains(&config.baud_rate) {
+                    config.baud_rate.to_string()
+                } else {
+                    "Custom".to_string()
+                };
+                egui::ComboBox
+                    ::from_id_source("connection_baud_combo_box")
+                    .width(100.0)
+                    .selected_text(selected)
+                    .show_ui(ui_row, |ui_combobox| {
+                        for rate in STANDARD_BAUD_RATES {
+                            ui_combobox.selectable_value(
+                                &mut config.baud_rate,
+                                rate,
+                                rate.to_string()
+                            );
+                        }
+                    });
+                // any rate the UART can divide down to may be typed in here
+                ui_row
+                    .add(egui::DragValue::new(&mut config.baud_rate).range(50..=20_000_000))
+                    .on_hover_text("Custom (non-standard) baud rate");
+            });
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Data Bits");
+            egui::ComboBox
+                ::from_id_source("connection_data_bits_combo_box")
+                .width(100.0)
+                .selected_text(u8::from(config.data_bits).to_string())
+                .show_ui(ui_grid, |ui_combobox| {
+                    for bits in DATA_BITS {
+                        ui_combobox.selectable_value(
+                            &mut config.data_bits,
+                            bits,
+                            u8::from(bits).to_string()
+                        );
+                    }
+                });
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Parity");
+            egui::ComboBox
+                ::from_id_source("connection_parity_combo_box")
+                .width(100.0)
+                .selected_text(config.parity.to_string())
+                .show_ui(ui_grid, |ui_combobox| {
+                    for parity in PARITIES {
+                        ui_combobox.selectable_value(
+                            &mut config.parity,
+                            parity,
+                            parity.to_string()
+                        );
+                    }
+                });
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Stop Bits");
+            egui::ComboBox
+                ::from_id_source("connection_stop_bits_combo_box")
+                .width(100.0)
+                .selected_text(u8::from(config.stop_bits).to_string())
+                .show_ui(ui_grid, |ui_combobox| {
+                    for bits in STOP_BITS {
+                        ui_combobox.selectable_value(
+                            &mut config.stop_bits,
+                            bits,
+                            u8::from(bits).to_string()
+                        );
+                    }
+                });
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Flow Control");
+            egui::ComboBox
+                ::from_id_source("connection_flow_combo_box")
+                .width(100.0)
+                .selected_text(config.flow_control.to_string())
+                .show_ui(ui_grid, |ui_combobox| {
+                    for flow in FLOW_CONTROLS {
+                        ui_combobox.selectable_value(
+                            &mut config.flow_control,
+                            flow,
+                            flow.to_string()
+                        );
+                    }
+                });
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Read Timeout");
+            ui_grid.add(
+                egui::DragValue::new(&mut config.timeout_ms).range(1..=60_000).suffix(" ms")
+            );
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Line Settings");
+            ui_grid.label(egui::RichText::new(config.summary()).monospace());
+            ui_grid.end_row();
+        });
+
+    action
+}
+
+/// Renders the state of the connection with a Disconnect button while it is active
+pub fn connection_status_ui(
+    ui_row: &mut egui::Ui,
+    connection: &Connection
+) -> Option<ConnectionAction> {
+    let colour = match connection.state {
+        ConnectionState::Connected => egui::Color32::GREEN,
+        ConnectionState::Connecting | ConnectionState::Closing => egui::Color32::YELLOW,
+        ConnectionState::Disconnected => egui::Color32::GRAY,
+    };
+    let status = match connection.active_port() {
+        Some(port_name) => format!("● {} {}", connection.state.label(), port_name),
+        None => format!("● {}", connection.state.label()),
+    };
+    ui_row.label(egui::RichText::new(status).color(colour));
+
+    let active = matches!(
+        connection.state,
+        ConnectionState::Connecting | ConnectionState::Connected
+    );
+    if active && ui_row.button("Disconnect").clicked() {
+        return Some(ConnectionAction::Disconnect);
+    }
+    None
+}
@@ -0,0 +1,113 @@
+//! Find bar above the terminal view, opened with Ctrl+F
+
+use eframe::egui;
+
+use crate::models::scrollback::Scrollback;
+use crate::models::search::{ FilterMode, Search, FILTER_MODES };
+
+/// Renders the find bar while it is open. Enter and F3 select the next matching line,
+/// Shift+Enter and Shift+F3 the previous one, Escape closes the bar. Ctrl+F and F3 only work
+/// with `shortcuts`, for one find bar at a time.
+pub fn find_bar_ui(
+    ui_root: &mut egui::Ui,
+    search: &mut Search,
+    scrollback: &Scrollback,
+    shortcuts: bool
+) {
+    let input_id = ui_root.make_persistent_id("find_input");
+    // only the find bar with the shortcuts may take Ctrl+F from the others
+    let ctrl_f =
+        shortcuts && ui_root.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::F));
+    if ctrl_f {
+        search.open = true;
+        ui_root.memory_mut(|memory| memory.request_focus(input_id));
+    }
+    if !search.open {
+        return;
+    }
+    let navigable = search.match_count() > 0 && search.filter != FilterMode::NotMatching;
+    let (next, previous) = match shortcuts {
+        true => ui_root.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::F3),
+                i.consume_key(egui::Modifiers::SHIFT, egui::Key::F3),
+            )
+        }),
+        false => (false, false),
+    };
+    let (mut next, mut previous) = (next && navigable, previous && navigable);
+
+    ui_root.horizontal(|ui_row| {
+        ui_row.label("Find");
+        let input = ui_row.add(
+            egui::TextEdit
+                ::singleline(&mut search.query.text)
+                .id(input_id)
+                .font(egui::TextStyle::Monospace)
+                .hint_text("Text in the terminal, Enter for the next line")
+                .desired_width(250.0)
+        );
+        // a single line input gives up the focus on Enter and Escape
+        if input.lost_focus() {
+            let (enter, shift, escape) = ui_row.input(|i| {
+                (
+                    i.key_pressed(egui::Key::Enter),
+                    i.modifiers.shift,
+                    i.key_pressed(egui::Key::Escape),
+                )
+            });
+            if escape {
+                search.open = false;
+            } else if enter {
+                next |= navigable && !shift;
+                previous |= navigable && shift;
+                input.request_focus();
+            }
+        }
+        ui_row.checkbox(&mut search.query.regex, ".*").on_hover_text("Regex");
+        ui_row.checkbox(&mut search.query.case_sensitive, "Aa").on_hover_text("Case sensitive");
+
+        let previous_button = ui_row
+            .add_enabled(navigable, egui::Button::new("▲"))
+            .on_hover_text("Previous line, Shift+Enter");
+        previous |= previous_button.clicked();
+        let next_button = ui_row
+            .add_enabled(navigable, egui::Button::new("▼"))
+            .on_hover_text("Next line, Enter");
+        next |= next_button.clicked();
+
+        egui::ComboBox
+            ::from_id_source("find_filter_combo_box")
+            .width(100.0)
+            .selected_text(search.filter.label())
+            .show_ui(ui_row, |ui_combobox| {
+                for filter in FILTER_MODES {
+                    ui_combobox.selectable_value(&mut search.filter, filter, filter.label());
+                }
+            })
+            .response.on_hover_text("Lines shown, the capture goes on underneath");
+
+        match (&search.error, search.current_position()) {
+            (Some(err), _) => {
+                ui_row.colored_label(egui::Color32::RED, err.lines().last().unwrap_or(err));
+            }
+            _ if search.query.text.is_empty() => {}
+            (None, Some(position)) => {
+                ui_row.label(format!("{} of {} lines", position, search.match_count()));
+            }
+            (None, None) => {
+                ui_row.label(format!("{} lines", search.match_count()));
+            }
+        }
+
+        if ui_row.button("✖").on_hover_text("Close, Escape").clicked() {
+            search.open = false;
+        }
+    });
+
+    if previous {
+        search.previous(scrollback);
+    } else if next {
+        search.next(scrollback);
+    }
+}
@@ -0,0 +1,153 @@
+//! Highlight rule editor of the Project tab, and the terminal lines drawn with the rules, their
+//! ANSI styles and the matches of the find bar
+
+use eframe::egui;
+use egui::text::{ LayoutJob, TextFormat };
+
+use crate::models::ansi::styled;
+use crate::models::highlight::{
+    default_rules,
+    overlay,
+    HighlightRule,
+    Highlighter,
+    Rgb,
+    Style,
+};
+use crate::models::search::Search;
+
+pub const TEXT_SIZE: f32 = 12.0;
+
+fn grid_label(ui_grid: &mut egui::Ui, text: &str) {
+    ui_grid.add(egui::Label::new(egui::RichText::new(text).size(TEXT_SIZE).monospace()));
+}
+
+fn color32(rgb: Rgb) -> egui::Color32 {
+    egui::Color32::from_rgb(rgb.0[0], rgb.0[1], rgb.0[2])
+}
+
+/// The text format of `style` over the terminal colour, the monospace font has no bold face so
+/// bold text is drawn brighter
+pub fn text_format(style: &Style, font_id: &egui::FontId, color: egui::Color32) -> TextFormat {
+    let mut foreground = style.foreground.map_or(color, color32);
+    let mut background = style.background.map_or(egui::Color32::TRANSPARENT, color32);
+    if style.inverse {
+        (foreground, background) = (
+            style.background.map_or(egui::Color32::BLACK, color32),
+            foreground,
+        );
+    }
+    if style.bold {
+        foreground = foreground.lerp_to_gamma(egui::Color32::WHITE, 0.35);
+    }
+    TextFormat {
+        font_id: font_id.clone(),
+        color: foreground,
+        background,
+        italics: style.italic,
+        underline: if style.underline {
+            egui::Stroke::new(1.0, foreground)
+        } else {
+            egui::Stroke::NONE
+        },
+        ..Default::default()
+    }
+}
+
+/// `line` laid out with its ANSI styles, the highlight rules drawn over them and the matches
+/// of the find bar over everything, plain text in `color`
+pub fn styled_line(
+    line: &str,
+    highlighter: &Highlighter,
+    search: &Search,
+    current: bool,
+    font_id: &egui::FontId,
+    color: egui::Color32
+) -> LayoutJob {
+    let (text, ansi) = styled(line);
+    let spans = overlay(&ansi, &highlighter.spans(&text));
+    let spans = overlay(&spans, &search.spans(&text, current));
+    let mut job = LayoutJob::default();
+    for (range, style) in spans {
+        job.append(&text[range], 0.0, text_format(&style, font_id, color));
+    }
+    job
+}
+
+/// A colour that can be left unset, to keep the terminal colour
+fn optional_color_ui(ui_grid: &mut egui::Ui, color: &mut Option<Rgb>, default: Rgb) {
+    ui_grid.horizontal(|ui_row| {
+        let mut set = color.is_some();
+        if ui_row.checkbox(&mut set, "").changed() {
+            *color = set.then_some(default);
+        }
+        if let Some(rgb) = color.as_mut() {
+            ui_row.color_edit_button_srgb(&mut rgb.0);
+        }
+    });
+}
+
+/// Renders the highlight rules of the project, and the errors of the ones that do not compile
+pub fn highlights_ui(
+    ui_root: &mut egui::Ui,
+    rules: &mut Vec<HighlightRule>,
+    errors: &[String]
+) {
+    ui_root.horizontal(|ui_row| {
+        ui_row.heading("Highlights");
+        if ui_row.button(egui::RichText::new("Add Rule").color(egui::Color32::GREEN)).clicked() {
+            rules.push(HighlightRule {
+                name: format!("Rule {}", rules.len() + 1),
+                foreground: Some(Rgb([0xff, 0xb8, 0x6c])),
+                ..Default::default()
+            });
+        }
+        if ui_row.button("Add Defaults").on_hover_text("Errors, warnings and addresses").clicked() {
+            rules.extend(default_rules());
+        }
+    });
+    if rules.is_empty() {
+        ui_root.label("Text matching a rule's regex is coloured in the terminal");
+    }
+    for error in errors {
+        ui_root.colored_label(egui::Color32::RED, error);
+    }
+    if rules.is_empty() {
+        return;
+    }
+
+    let mut remove = None;
+    egui::Grid
+        ::new("highlights_grid")
+        .num_columns(8)
+        .striped(true)
+        .show(ui_root, |ui_grid| {
+            for header in ["On", "Name", "Pattern", "Aa", "Text", "Background", "Bold", ""] {
+                grid_label(ui_grid, header);
+            }
+            ui_grid.end_row();
+            for (index, rule) in rules.iter_mut().enumerate() {
+                ui_grid.checkbox(&mut rule.enabled, "");
+                ui_grid.add(egui::TextEdit::singleline(&mut rule.name).desired_width(100.0));
+                ui_grid.add(
+                    egui::TextEdit
+                        ::singleline(&mut rule.pattern)
+                        .hint_text(r"i.e. \bERROR\b")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(200.0)
+                );
+                ui_grid
+                    .checkbox(&mut rule.case_sensitive, "")
+                    .on_hover_text("Case sensitive");
+                optional_color_ui(ui_grid, &mut rule.foreground, Rgb([0xff, 0xff, 0xff]));
+                optional_color_ui(ui_grid, &mut rule.background, Rgb([0x44, 0x47, 0x5a]));
+                ui_grid.checkbox(&mut rule.bold, "");
+                if ui_grid.button("✖").on_hover_text("Remove the rule").clicked() {
+                    remove = Some(index);
+                }
+                ui_grid.end_row();
+            }
+        });
+    if let Some(index) = remove {
+        rules.remove(index);
+    }
+}
@@ -1,19 +1,12 @@
 
 use crate::models::details::Details;
 
+#[derive(Default)]
 pub struct Logger {
     pub logger_text: String,
 
 }
 
-impl Default for Logger {
-    fn default() -> Self {
-        Self {
-            logger_text: String::new(),
-        }
-    }
-}
-
 impl Logger {
 
     pub fn clear(&mut self) -> String {
@@ -1,4 +1,13 @@
+pub mod connection; 
+pub mod find;
+pub mod highlights;
 pub mod logger; 
+pub mod parsers;
+pub mod plot;
 pub mod project; 
+pub mod sessions;
 pub mod settings; 
+pub mod toasts;
+pub mod transmit; 
+pub mod triggers;
 
@@ -0,0 +1,187 @@
+//! Parser editor of the Project tab, how received lines become channels
+
+use eframe::egui;
+
+use crate::models::parsers::{
+    ChannelConfig,
+    ParserConfig,
+    ParserKind,
+    DELIMITERS,
+    PARSER_KINDS,
+};
+
+pub const TEXT_SIZE: f32 = 12.0;
+pub const TEXT_EDIT_WIDTH: f32 = 300.0;
+
+fn grid_label(ui_grid: &mut egui::Ui, text: &str) {
+    ui_grid.add(egui::Label::new(egui::RichText::new(text).size(TEXT_SIZE).monospace()));
+}
+
+/// Renders the parsers of the project with their channels, and the errors of the ones that
+/// could not be built
+pub fn parsers_ui(ui_root: &mut egui::Ui, parsers: &mut Vec<ParserConfig>, errors: &[String]) {
+    ui_root.horizontal(|ui_row| {
+        ui_row.heading("Parsers");
+        if ui_row.button(egui::RichText::new("Add Parser").color(egui::Color32::GREEN)).clicked() {
+            parsers.push(ParserConfig {
+                name: format!("Parser {}", parsers.len() + 1),
+                ..Default::default()
+            });
+        }
+    });
+    if parsers.is_empty() {
+        ui_root.label(
+            "Without parsers, numbers named like temp=21.5 or rpm 1200 are plotted and logged"
+        );
+    }
+    for error in errors {
+        ui_root.colored_label(egui::Color32::RED, error);
+    }
+
+    let mut remove = None;
+    for (index, parser) in parsers.iter_mut().enumerate() {
+        egui::CollapsingHeader
+            ::new(format!("{} ({})", parser.name, parser.kind.label()))
+            .id_source(("parser", index))
+            .default_open(true)
+            .show(ui_root, |ui_parser| {
+                parser_ui(ui_parser, index, parser);
+                let remove_text = egui::RichText::new("Remove Parser").color(egui::Color32::RED);
+                if ui_parser.button(remove_text).clicked() {
+                    remove = Some(index);
+                }
+            });
+    }
+    if let Some(index) = remove {
+        parsers.remove(index);
+    }
+}
+
+fn parser_ui(ui_root: &mut egui::Ui, index: usize, parser: &mut ParserConfig) {
+    egui::Grid
+        ::new(("parser_grid", index))
+        .num_columns(2)
+        .striped(true)
+        .show(ui_root, |ui_grid| {
+            grid_label(ui_grid, "Enabled");
+            ui_grid.checkbox(&mut parser.enabled, "");
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Name");
+            ui_grid.add(
+                egui::TextEdit::singleline(&mut parser.name).desired_width(TEXT_EDIT_WIDTH)
+            );
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Kind");
+            egui::ComboBox
+                ::from_id_source(("parser_kind_combo_box", index))
+                .width(120.0)
+                .selected_text(parser.kind.label())
+                .show_ui(ui_grid, |ui_combobox| {
+                    for kind in PARSER_KINDS {
+                        ui_combobox.selectable_value(&mut parser.kind, kind, kind.label());
+                    }
+                });
+            ui_grid.end_row();
+
+            grid_label(ui_grid, "Prefix");
+            ui_grid.add(
+                egui::TextEdit
+                    ::singleline(&mut parser.prefix)
+                    .hint_text("Only lines starting with this, i.e. $ADC,")
+                    .desired_width(TEXT_EDIT_WIDTH)
+            );
+            ui_grid.end_row();
+
+            match parser.kind {
+                ParserKind::Delimited => {
+                    grid_label(ui_grid, "Delimiter");
+                    egui::ComboBox
+                        ::from_id_source(("parser_delimiter_combo_box", index))
+                        .width(120.0)
+                        .selected_text(parser.delimiter.label())
+                        .show_ui(ui_grid, |ui_combobox| {
+                            for delimiter in DELIMITERS {
+                                ui_combobox.selectable_value(
+                                    &mut parser.delimiter,
+                                    delimiter,
+                                    delimiter.label()
+                                );
+                            }
+                        });
+                    ui_grid.end_row();
+
+                    grid_label(ui_grid, "Columns");
+                    let mut columns = parser.columns.join(",");
+                    let edit = ui_grid.add(
+                        egui::TextEdit
+                            ::singleline(&mut columns)
+                            .hint_text("Channel of each column, i.e. ch0,ch1,,state")
+                            .desired_width(TEXT_EDIT_WIDTH)
+                    );
+                    if edit.changed() {
+                        parser.columns = if columns.trim().is_empty() {
+                            Vec::new()
+                        } else {
+                            columns.split(',').map(|name| name.trim().to_string()).collect()
+                        };
+                    }
+                    ui_grid.end_row();
+                }
+                ParserKind::KeyValue => {
+                    grid_label(ui_grid, "Separator");
+                    ui_grid.add(
+                        egui::TextEdit::singleline(&mut parser.separator).desired_width(40.0)
+                    );
+                    ui_grid.end_row();
+                }
+                ParserKind::Regex => {
+                    grid_label(ui_grid, "Pattern");
+                    ui_grid.add(
+                        egui::TextEdit
+                            ::singleline(&mut parser.pattern)
+                            .hint_text(r"Named groups are channels, i.e. T=(?P<temp>-?\d+)")
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(TEXT_EDIT_WIDTH)
+                    );
+                    ui_grid.end_row();
+                }
+            }
+        });
+
+    ui_root.horizontal(|ui_row| {
+        ui_row.label("Channels");
+        let add = ui_row.button("Add Channel").on_hover_text("Unit, scale and offset of a channel");
+        if add.clicked() {
+            parser.channels.push(ChannelConfig::default());
+        }
+    });
+    if parser.channels.is_empty() {
+        return;
+    }
+    let mut remove = None;
+    egui::Grid
+        ::new(("parser_channels_grid", index))
+        .num_columns(5)
+        .striped(true)
+        .show(ui_root, |ui_grid| {
+            for header in ["Name", "Unit", "Scale", "Offset", ""] {
+                grid_label(ui_grid, header);
+            }
+            ui_grid.end_row();
+            for (channel_index, channel) in parser.channels.iter_mut().enumerate() {
+                ui_grid.add(egui::TextEdit::singleline(&mut channel.name).desired_width(100.0));
+                ui_grid.add(egui::TextEdit::singleline(&mut channel.unit).desired_width(50.0));
+                ui_grid.add(egui::DragValue::new(&mut channel.scale).speed(0.01));
+                ui_grid.add(egui::DragValue::new(&mut channel.offset).speed(0.1));
+                if ui_grid.button("✖").on_hover_text("Remove the channel").clicked() {
+                    remove = Some(channel_index);
+                }
+                ui_grid.end_row();
+            }
+        });
+    if let Some(channel_index) = remove {
+        parser.channels.remove(channel_index);
+    }
+}
@@ -0,0 +1,71 @@
+//! Plot Tab, live chart of the numeric values found in received lines
+
+use eframe::egui;
+use egui_plot::{ Legend, Line, Plot, PlotBounds, PlotMemory, PlotPoints };
+
+use crate::models::plot::PlotData;
+
+pub const PLOT_HEIGHT: f32 = 300.0;
+
+/// Renders the plot controls and the chart, the window follows the newest values unless
+/// paused, a paused plot can be dragged and zoomed. Clicking a legend entry hides its series.
+pub fn plot_tab_ui(ui_root: &mut egui::Ui, plot: &mut PlotData) {
+    let paused = plot.is_paused();
+
+    ui_root.add_space(10.0);
+    ui_root.horizontal(|ui_row| {
+        let (label, color) = if paused {
+            ("Resume", egui::Color32::GREEN)
+        } else {
+            ("Pause", egui::Color32::YELLOW)
+        };
+        if ui_row.button(egui::RichText::new(label).color(color)).clicked() {
+            plot.set_paused(!paused);
+        }
+        if ui_row.button(egui::RichText::new("Clear").color(egui::Color32::GREEN)).clicked() {
+            plot.clear();
+        }
+        ui_row.label("Window");
+        ui_row
+            .add(
+                egui::DragValue
+                    ::new(&mut plot.window_secs)
+                    .speed(0.5)
+                    .range(1.0..=3600.0)
+                    .suffix(" s")
+            )
+            .on_hover_text("Seconds shown, the oldest values scroll off to the left");
+        ui_row.label(format!("{} series", plot.series.len()));
+    });
+
+    // series hidden by clicking the legend do not count for the autoscale
+    let plot_id = ui_root.make_persistent_id("serial_plot");
+    if let Some(memory) = PlotMemory::load(ui_root.ctx(), plot_id) {
+        plot.hidden = memory.hidden_items.into_iter().collect();
+    }
+
+    let (start, end) = plot.window();
+    let (min, max) = plot.value_range().unwrap_or((0.0, 1.0));
+    // keep a flat line off the edges
+    let margin = if max > min { (max - min) * 0.05 } else { 1.0 };
+
+    Plot::new("serial_plot")
+        .id(plot_id)
+        .legend(Legend::default())
+        .height(PLOT_HEIGHT)
+        .x_axis_label("seconds")
+        .allow_drag(paused)
+        .allow_zoom(paused)
+        .allow_scroll(paused)
+        .show(ui_root, |plot_ui| {
+            for series in &plot.series {
+                let points = PlotPoints::new(plot.visible(series));
+                plot_ui.line(Line::new(points).name(&series.name));
+            }
+            if !paused {
+                plot_ui.set_plot_bounds(
+                    PlotBounds::from_min_max([start, min - margin], [end, max + margin])
+                );
+            }
+        });
+}
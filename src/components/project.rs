@@ -1,7 +1,26 @@
 
 
+use std::path::PathBuf;
+
 use egui_file_dialog::FileDialog;
 
+use crate::models::capture::{
+    CaptureWriter,
+    Replay,
+    ReplaySpeed,
+    CAPTURE_EXTENSION,
+    REPLAY_SPEEDS,
+};
+use crate::components::highlights::highlights_ui;
+use crate::components::parsers::parsers_ui;
+use crate::components::triggers::triggers_ui;
+use crate::models::data_log::DataLog;
+use crate::models::highlight::{ default_rules, HighlightRule };
+use crate::models::history::HISTORY_FILE_NAME;
+use crate::models::parsers::ParserConfig;
+use crate::models::project_file::{ ProjectFile, PROJECT_FILE_NAME };
+use crate::models::trigger::TriggerRule;
+
 #[derive(Default)]
 pub struct Project {
     pub id: i32,
@@ -14,6 +33,16 @@ pub struct Project {
     pub updated_at: String,
     pub repo_url: String,
     pub company: String,
+    /// Picks the capture to replay
+    pub replay_dialog: FileDialog,
+    pub replay_path: String,
+    pub replay_speed: ReplaySpeed,
+    /// How received lines become channels
+    pub parsers: Vec<ParserConfig>,
+    /// How the terminal colours what it shows
+    pub highlights: Vec<HighlightRule>,
+    /// What received lines set off
+    pub triggers: Vec<TriggerRule>,
 }
 
 impl Project {
@@ -29,19 +58,152 @@ impl Project {
             updated_at   : String::new(),
             repo_url     : String::new(),
             company      : String::new(),
+            replay_dialog: FileDialog::new(),
+            replay_path  : String::new(),
+            replay_speed : ReplaySpeed::default(),
+            parsers      : Vec::new(),
+            highlights   : default_rules(),
+            triggers     : Vec::new(),
+        }
+    }
+
+    /// Where the data log of session `session` goes, `project_dir/project_name` with `.csv`
+    /// added if there is no extension, and the session number after the first session
+    pub fn log_path(&self, session: usize) -> Result<PathBuf, String> {
+        let name = self.project_name.trim();
+        if name.is_empty() {
+            return Err("Set a Project Name in the Project tab to start logging".to_string());
+        }
+        let dir = self.project_dir.trim();
+        if dir.is_empty() {
+            return Err(
+                "Select a Project Directory in the Project tab to start logging".to_string()
+            );
+        }
+        let mut path = PathBuf::from(dir).join(name);
+        if path.extension().is_none() {
+            path.set_extension("csv");
+        }
+        if session > 1 {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = path.extension().unwrap_or_default().to_string_lossy();
+            path = path.with_file_name(format!("{}-{}.{}", stem, session, extension));
+        }
+        Ok(path)
+    }
+
+    /// Where the capture of session `session` goes, next to its data log
+    pub fn capture_path(&self, session: usize) -> Result<PathBuf, String> {
+        Ok(self.log_path(session)?.with_extension(CAPTURE_EXTENSION))
+    }
+
+    /// The header block of the data log, with the unit of every channel that has one
+    pub fn log_header(&self) -> Vec<(String, String)> {
+        let mut header = vec![
+            ("project".to_string(), self.project_name.clone()),
+            ("module".to_string(), self.module_name.clone()),
+            ("description".to_string(), self.description.clone()),
+            ("company".to_string(), self.company.clone()),
+            ("repo".to_string(), self.repo_url.clone())
+        ];
+        for parser in self.parsers.iter().filter(|parser| parser.enabled) {
+            for channel in parser.channels.iter().filter(|channel| !channel.unit.is_empty()) {
+                header.push((format!("unit {}", channel.name), channel.unit.clone()));
+            }
+        }
+        header
+    }
+
+    /// Where the project is saved, in the project directory
+    pub fn file_path(&self) -> Result<PathBuf, String> {
+        let dir = self.project_dir.trim();
+        if dir.is_empty() {
+            return Err("Select a Project Directory to save or load the project".to_string());
+        }
+        Ok(PathBuf::from(dir).join(PROJECT_FILE_NAME))
+    }
+
+    /// Where the transmit history is kept, in the project directory, or the local data
+    /// directory while there is none. None while the directory typed does not exist.
+    pub fn history_path(&self) -> Option<PathBuf> {
+        let dir = self.project_dir.trim();
+        if dir.is_empty() {
+            return dirs::data_local_dir().map(|dir| dir.join("arrakis").join(HISTORY_FILE_NAME));
+        }
+        let dir = PathBuf::from(dir);
+        dir.is_dir().then(|| dir.join(HISTORY_FILE_NAME))
+    }
+
+    /// The settings saved in the project file
+    pub fn to_file(&self) -> ProjectFile {
+        ProjectFile {
+            project_name: self.project_name.clone(),
+            module_name: self.module_name.clone(),
+            description: self.description.clone(),
+            created_at: self.created_at.clone(),
+            updated_at: self.updated_at.clone(),
+            repo_url: self.repo_url.clone(),
+            company: self.company.clone(),
+            parsers: self.parsers.clone(),
+            highlights: self.highlights.clone(),
+            triggers: self.triggers.clone(),
         }
     }
+
+    /// Takes the settings of a loaded project file
+    pub fn apply_file(&mut self, file: ProjectFile) {
+        self.project_name = file.project_name;
+        self.module_name = file.module_name;
+        self.description = file.description;
+        self.created_at = file.created_at;
+        self.updated_at = file.updated_at;
+        self.repo_url = file.repo_url;
+        self.company = file.company;
+        self.parsers = file.parsers;
+        self.highlights = file.highlights;
+        self.triggers = file.triggers;
+    }
+}
+
+/// What the user asked for in the Project tab
+pub enum ProjectAction {
+    StartLogging,
+    StopLogging,
+    StartCapture,
+    StopCapture,
+    StartReplay,
+    StopReplay,
+    Save,
+    Load,
+}
+
+/// What is being recorded or replayed, shown next to the controls
+pub struct ProjectStatus<'a> {
+    /// Number of the active session, the files of sessions after the first are numbered
+    pub session: usize,
+    pub data_log: Option<&'a DataLog>,
+    pub capture: Option<&'a CaptureWriter>,
+    pub replay: Option<&'a Replay>,
+    /// Parsers of the project that could not be built
+    pub parser_errors: &'a [String],
+    /// Highlight rules that do not compile
+    pub highlight_errors: &'a [String],
+    /// Triggers that do not compile
+    pub trigger_errors: &'a [String],
 }
 
 pub const TEXT_SIZE: f32 = 12.0;
 pub const TEXT_EDIT_WIDTH: f32 = 400.0;
 
 
+/// Renders the project settings and the data logging controls
 pub fn project_tab_ui(
     ctx: &egui::Context,
     ui_root: &mut egui::Ui,
     project: &mut Project,
-) {
+    status: ProjectStatus,
+) -> Option<ProjectAction> {
+    let mut action = None;
     egui::ScrollArea::new([false, true])
         .id_source("settings_tab_scroll_area")
         .max_width(800.)
@@ -148,7 +310,146 @@ pub fn project_tab_ui(
 
                         ui_grid.end_row();
                     });
+
+                    ui_layout.add_space(10.0);
+                    ui_layout.horizontal(|ui_row| {
+                        match status.data_log {
+                            Some(log) => {
+                                let stop = egui::RichText::new("Stop Logging")
+                                    .color(egui::Color32::RED);
+                                if ui_row.button(stop).clicked() {
+                                    action = Some(ProjectAction::StopLogging);
+                                }
+                                ui_row.label(format!(
+                                    "● Logging to {}, {} lines",
+                                    log.path.display(),
+                                    log.lines_written
+                                ));
+                            }
+                            None => {
+                                let start = egui::RichText::new("Start Logging")
+                                    .color(egui::Color32::GREEN);
+                                let button = ui_row
+                                    .button(start)
+                                    .on_hover_text("Write received and sent lines as CSV");
+                                if button.clicked() {
+                                    action = Some(ProjectAction::StartLogging);
+                                }
+                                match project.log_path(status.session) {
+                                    Ok(path) => ui_row.label(path.display().to_string()),
+                                    Err(err) => ui_row.label(err),
+                                };
+                            }
+                        }
+                    });
+
+                    ui_layout.horizontal(|ui_row| {
+                        match status.capture {
+                            Some(capture) => {
+                                let stop = egui::RichText::new("Stop Capture")
+                                    .color(egui::Color32::RED);
+                                if ui_row.button(stop).clicked() {
+                                    action = Some(ProjectAction::StopCapture);
+                                }
+                                ui_row.label(format!(
+                                    "● Capturing to {}, {} bytes",
+                                    capture.path.display(),
+                                    capture.bytes_written
+                                ));
+                            }
+                            None => {
+                                let start = egui::RichText::new("Start Capture")
+                                    .color(egui::Color32::GREEN);
+                                let button = ui_row
+                                    .button(start)
+                                    .on_hover_text("Record every byte received and sent");
+                                if button.clicked() {
+                                    action = Some(ProjectAction::StartCapture);
+                                }
+                                if let Ok(path) = project.capture_path(status.session) {
+                                    ui_row.label(path.display().to_string());
+                                }
+                            }
+                        }
+                    });
+
+                    ui_layout.add_space(10.0);
+                    ui_layout.horizontal(|ui_row| {
+                        match status.replay {
+                            Some(replay) => {
+                                let stop = egui::RichText::new("Stop Replay")
+                                    .color(egui::Color32::RED);
+                                if ui_row.button(stop).clicked() {
+                                    action = Some(ProjectAction::StopReplay);
+                                }
+                                let (done, total) = replay.progress();
+                                ui_row.label(format!(
+                                    "● Replaying {}, {} of {} chunks",
+                                    replay.path.display(),
+                                    done,
+                                    total
+                                ));
+                            }
+                            None => {
+                                let start = egui::RichText::new("Replay")
+                                    .color(egui::Color32::GREEN);
+                                let can_replay = !project.replay_path.trim().is_empty();
+                                let button = ui_row
+                                    .add_enabled(can_replay, egui::Button::new(start))
+                                    .on_hover_text("Feed a capture into the terminal");
+                                if button.clicked() {
+                                    action = Some(ProjectAction::StartReplay);
+                                }
+                                let path_edit = ui_row.add(
+                                    egui::TextEdit::singleline(&mut project.replay_path)
+                                        .hint_text("Double click to select a capture")
+                                        .desired_width(TEXT_EDIT_WIDTH * 0.75),
+                                );
+                                if path_edit.double_clicked_by(egui::PointerButton::Primary) {
+                                    project.replay_dialog.select_file();
+                                }
+                                if let Some(path) = project.replay_dialog.update(ctx).selected() {
+                                    project.replay_path = path.to_string_lossy().to_string();
+                                }
+                                egui::ComboBox::from_id_source("replay_speed_combo_box")
+                                    .width(80.0)
+                                    .selected_text(project.replay_speed.label())
+                                    .show_ui(ui_row, |ui_combobox| {
+                                        for speed in REPLAY_SPEEDS {
+                                            ui_combobox.selectable_value(
+                                                &mut project.replay_speed,
+                                                speed,
+                                                speed.label(),
+                                            );
+                                        }
+                                    });
+                            }
+                        }
+                    });
+
+                    ui_layout.add_space(10.0);
+                    ui_layout.horizontal(|ui_row| {
+                        if ui_row.button("Save Project").clicked() {
+                            action = Some(ProjectAction::Save);
+                        }
+                        if ui_row.button("Load Project").clicked() {
+                            action = Some(ProjectAction::Load);
+                        }
+                        if let Ok(path) = project.file_path() {
+                            ui_row.label(path.display().to_string());
+                        }
+                    });
+
+                    ui_layout.add_space(10.0);
+                    parsers_ui(ui_layout, &mut project.parsers, status.parser_errors);
+
+                    ui_layout.add_space(10.0);
+                    highlights_ui(ui_layout, &mut project.highlights, status.highlight_errors);
+
+                    ui_layout.add_space(10.0);
+                    triggers_ui(ui_layout, &mut project.triggers, status.trigger_errors);
                 },
             );
         });
+    action
 }
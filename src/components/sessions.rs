@@ -0,0 +1,52 @@
+//! Session tabs above the terminal, to switch between the open ports or show them side by side
+
+use eframe::egui;
+
+/// What the user asked for in the session tabs
+pub enum SessionAction {
+    Add,
+    /// Close the session at this index
+    Close(usize),
+}
+
+/// Renders a tab per session from their `labels`, the connected ones marked, with buttons to
+/// open and close sessions
+pub fn session_tabs_ui(
+    ui_root: &mut egui::Ui,
+    labels: &[(String, bool)],
+    active: &mut usize,
+    side_by_side: &mut bool
+) -> Option<SessionAction> {
+    let mut action = None;
+    ui_root.horizontal_wrapped(|ui_row| {
+        for (index, (label, connected)) in labels.iter().enumerate() {
+            let text = if *connected {
+                egui::RichText::new(format!("● {}", label)).color(egui::Color32::GREEN)
+            } else {
+                egui::RichText::new(label)
+            };
+            ui_row.selectable_value(active, index, text);
+            if labels.len() > 1 {
+                let close = ui_row
+                    .small_button("✖")
+                    .on_hover_text("Close the session, its port and files");
+                if close.clicked() {
+                    action = Some(SessionAction::Close(index));
+                }
+            }
+            ui_row.separator();
+        }
+        let add = ui_row
+            .button(egui::RichText::new("+").color(egui::Color32::GREEN))
+            .on_hover_text("New session for another port");
+        if add.clicked() {
+            action = Some(SessionAction::Add);
+        }
+        if labels.len() > 1 {
+            ui_row
+                .checkbox(side_by_side, "Side by side")
+                .on_hover_text("Show every session, the one clicked is active");
+        }
+    });
+    action
+}
@@ -0,0 +1,61 @@
+//! Messages popping up in the corner of the window for a few seconds
+
+use std::time::{ Duration, Instant };
+
+use eframe::egui;
+
+/// How long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+/// Toasts shown at once, the oldest go first
+const MAX_TOASTS: usize = 5;
+
+#[derive(Default)]
+pub struct Toasts {
+    /// Text and when it goes away, oldest first
+    toasts: Vec<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn new() -> Toasts {
+        Toasts::default()
+    }
+
+    pub fn push(&mut self, text: String) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push((text, Instant::now() + TOAST_DURATION));
+    }
+
+    /// Draws the toasts still up in the bottom right corner, clicking one closes it
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        self.toasts.retain(|(_, until)| *until > now);
+        let Some((_, next)) = self.toasts.first() else {
+            return;
+        };
+        ctx.request_repaint_after(*next - now);
+
+        let mut closed = None;
+        egui::Area
+            ::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui_area| {
+                for (index, (text, _)) in self.toasts.iter().enumerate() {
+                    let frame = egui::Frame::popup(ui_area.style()).show(ui_area, |ui_frame| {
+                        ui_frame.set_max_width(400.0);
+                        ui_frame.label(text);
+                    });
+                    let response = frame.response.interact(egui::Sense::click());
+                    if response.on_hover_text("Click to close").clicked() {
+                        closed = Some(index);
+                    }
+                }
+            });
+        if let Some(index) = closed {
+            self.toasts.remove(index);
+        }
+    }
+}
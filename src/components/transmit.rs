@@ -0,0 +1,178 @@
+//! Transmit bar, the input line below the terminal that writes to the open port
+
+use eframe::egui;
+use egui::text::{ CCursor, CCursorRange };
+
+use crate::models::command::{ classify, Input };
+use crate::models::completion::Completion;
+use crate::models::history::History;
+use crate::models::transmit::{ HistorySearch, Transmit, LINE_ENDINGS };
+
+/// Renders the input line with its send options, returning the line to send when the
+/// user presses Enter or clicks Send. Commands starting with `/` work without a connection.
+/// Up/Down browse the history, Ctrl+R searches it and Tab completes with `complete`.
+pub fn transmit_bar_ui(
+    ui_root: &mut egui::Ui,
+    transmit: &mut Transmit,
+    history: &mut History,
+    connected: bool,
+    complete: impl FnOnce(&str) -> Completion
+) -> Option<String> {
+    let mut send = None;
+    let input_id = ui_root.make_persistent_id("transmit_input");
+    let focused = ui_root.memory(|memory| memory.has_focus(input_id));
+    // the line put in the input by the keys below, the cursor is moved to its end
+    let mut replaced = None;
+
+    if transmit.search.is_some() {
+        // Escape drops the focus before the input sees it
+        if ui_root.input(|i| i.key_pressed(egui::Key::Escape)) {
+            transmit.search = None;
+            ui_root.memory_mut(|memory| memory.request_focus(input_id));
+        }
+    }
+    if focused {
+        match transmit.search.as_mut() {
+            Some(search) => {
+                let (older, enter) = ui_root.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::CTRL, egui::Key::R),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                    )
+                });
+                if older {
+                    let before = search.found.unwrap_or(history.entries().len());
+                    if let Some((index, _)) = history.search(&search.query, before) {
+                        search.found = Some(index);
+                    }
+                }
+                if enter {
+                    let found = search.found
+                        .and_then(|index| history.entries().get(index))
+                        .cloned();
+                    replaced = Some(found.unwrap_or_default());
+                    transmit.search = None;
+                }
+            }
+            None => {
+                let (up, down, search, tab) = ui_root.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                        i.consume_key(egui::Modifiers::CTRL, egui::Key::R),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                    )
+                });
+                if up {
+                    replaced = history.older(&transmit.input).map(str::to_string);
+                } else if down {
+                    replaced = history.newer().map(str::to_string);
+                } else if search {
+                    transmit.search = Some(HistorySearch::default());
+                } else if tab {
+                    let completion = complete(&transmit.input);
+                    replaced = completion.apply(&transmit.input);
+                    transmit.candidates = match replaced {
+                        Some(_) => Vec::new(),
+                        None => completion.candidates,
+                    };
+                }
+            }
+        }
+    }
+    if let Some(line) = &replaced {
+        transmit.input = line.clone();
+    }
+
+    if !transmit.candidates.is_empty() {
+        ui_root.label(
+            egui::RichText::new(transmit.candidates.join("   ")).monospace().weak()
+        );
+    }
+
+    ui_root.horizontal(|ui_row| {
+        let output = match transmit.search.as_mut() {
+            Some(search) => {
+                ui_row.label("reverse search:");
+                let output = egui::TextEdit
+                    ::singleline(&mut search.query)
+                    .id(input_id)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text("Enter takes the match, Ctrl+R finds an older one, Esc cancels")
+                    .desired_width(300.0)
+                    .lock_focus(true)
+                    .show(ui_row);
+                if output.response.changed() {
+                    let entries = history.entries().len();
+                    search.found = history
+                        .search(&search.query, entries)
+                        .map(|(index, _)| index);
+                }
+                output
+            }
+            None => {
+                egui::TextEdit
+                    ::singleline(&mut transmit.input)
+                    .id(input_id)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text(
+                        if connected {
+                            "Type a line to send, /help for commands"
+                        } else {
+                            "Not connected, /help for commands"
+                        }
+                    )
+                    .desired_width(300.0)
+                    .lock_focus(true)
+                    .show(ui_row)
+            }
+        };
+        let input = output.response.clone();
+        if input.changed() {
+            transmit.candidates.clear();
+        }
+        if let Some(line) = &replaced {
+            let mut state = output.state;
+            let end = CCursor::new(line.chars().count());
+            state.cursor.set_char_range(Some(CCursorRange::one(end)));
+            state.store(ui_row.ctx(), input_id);
+        }
+
+        if let Some(search) = &transmit.search {
+            let found = search.found.and_then(|index| history.entries().get(index));
+            ui_row.monospace(found.map_or("no match", String::as_str));
+            return;
+        }
+
+        let sendable = connected || matches!(classify(&transmit.input), Input::Command(_));
+        let entered = input.lost_focus() && ui_row.input(|i| i.key_pressed(egui::Key::Enter));
+
+        let send_button = egui::Button::new(
+            egui::RichText::new("Send").color(egui::Color32::GREEN)
+        );
+        let clicked = ui_row.add_enabled(sendable, send_button).clicked();
+
+        if sendable && (entered || clicked) {
+            send = Some(std::mem::take(&mut transmit.input));
+            transmit.candidates.clear();
+            // keep typing without clicking back into the input line
+            input.request_focus();
+        }
+
+        egui::ComboBox
+            ::from_id_source("transmit_line_ending_combo_box")
+            .width(60.0)
+            .selected_text(transmit.line_ending.label())
+            .show_ui(ui_row, |ui_combobox| {
+                for ending in LINE_ENDINGS {
+                    ui_combobox.selectable_value(&mut transmit.line_ending, ending, ending.label());
+                }
+            })
+            .response.on_hover_text("Line ending appended to every line sent");
+
+        ui_row.checkbox(&mut transmit.local_echo, "Echo");
+        ui_row.label(format!("{} bytes sent", transmit.bytes_sent));
+    });
+
+    send
+}
@@ -0,0 +1,178 @@
+//! Trigger editor of the Project tab, and the counters and bookmarks they fill on the Home tab
+
+use std::collections::BTreeMap;
+
+use eframe::egui;
+
+use crate::models::highlight::Rgb;
+use crate::models::trigger::{ Bookmark, TriggerRule, LOG_ACTIONS };
+
+pub const TEXT_SIZE: f32 = 12.0;
+
+fn grid_label(ui_grid: &mut egui::Ui, text: &str) {
+    ui_grid.add(egui::Label::new(egui::RichText::new(text).size(TEXT_SIZE).monospace()));
+}
+
+/// What the user asked for on the Home tab
+pub enum TriggerStatusAction {
+    ResetCounts,
+    ClearBookmarks,
+    /// Scroll the terminal to the bookmark at this index
+    Reveal(usize),
+}
+
+/// Renders the triggers of the project, and the errors of the ones that do not compile
+pub fn triggers_ui(ui_root: &mut egui::Ui, rules: &mut Vec<TriggerRule>, errors: &[String]) {
+    ui_root.horizontal(|ui_row| {
+        ui_row.heading("Triggers");
+        if ui_row.button(egui::RichText::new("Add Trigger").color(egui::Color32::GREEN)).clicked() {
+            rules.push(TriggerRule {
+                name: format!("Trigger {}", rules.len() + 1),
+                count: true,
+                ..Default::default()
+            });
+        }
+    });
+    if rules.is_empty() {
+        ui_root.label("A received line matching a trigger's regex fires its actions");
+    }
+    for error in errors {
+        ui_root.colored_label(egui::Color32::RED, error);
+    }
+    if rules.is_empty() {
+        return;
+    }
+
+    let mut remove = None;
+    egui::Grid
+        ::new("triggers_grid")
+        .num_columns(12)
+        .striped(true)
+        .show(ui_root, |ui_grid| {
+            let headers = [
+                "On", "Name", "Pattern", "Aa", "Highlight", "Bookmark", "Toast", "Log", "Send",
+                "Pause", "Count", "",
+            ];
+            for header in headers {
+                grid_label(ui_grid, header);
+            }
+            ui_grid.end_row();
+            for (index, rule) in rules.iter_mut().enumerate() {
+                ui_grid.checkbox(&mut rule.enabled, "");
+                ui_grid.add(egui::TextEdit::singleline(&mut rule.name).desired_width(100.0));
+                ui_grid.add(
+                    egui::TextEdit
+                        ::singleline(&mut rule.pattern)
+                        .hint_text(r"i.e. ASSERT (\w+)")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(160.0)
+                );
+                ui_grid
+                    .checkbox(&mut rule.case_sensitive, "")
+                    .on_hover_text("Case sensitive");
+                ui_grid.horizontal(|ui_row| {
+                    let mut set = rule.highlight.is_some();
+                    if ui_row.checkbox(&mut set, "").changed() {
+                        rule.highlight = set.then_some(Rgb([0x62, 0x72, 0xa4]));
+                    }
+                    if let Some(rgb) = rule.highlight.as_mut() {
+                        ui_row.color_edit_button_srgb(&mut rgb.0);
+                    }
+                });
+                ui_grid.checkbox(&mut rule.bookmark, "");
+                ui_grid.checkbox(&mut rule.toast, "");
+                egui::ComboBox
+                    ::from_id_source(("trigger_log_combo_box", index))
+                    .width(50.0)
+                    .selected_text(rule.log.label())
+                    .show_ui(ui_grid, |ui_combobox| {
+                        for log in LOG_ACTIONS {
+                            ui_combobox.selectable_value(&mut rule.log, log, log.label());
+                        }
+                    })
+                    .response.on_hover_text("Start or stop the data log");
+                ui_grid
+                    .add(
+                        egui::TextEdit
+                            ::singleline(&mut rule.send)
+                            .hint_text("i.e. dump $1")
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(100.0)
+                    )
+                    .on_hover_text("Line sent back, $1 is the first group of the pattern");
+                ui_grid
+                    .checkbox(&mut rule.pause, "")
+                    .on_hover_text("Pause the terminal, the data is still logged");
+                ui_grid.checkbox(&mut rule.count, "").on_hover_text("Count on the Home tab");
+                if ui_grid.button("✖").on_hover_text("Remove the trigger").clicked() {
+                    remove = Some(index);
+                }
+                ui_grid.end_row();
+            }
+        });
+    if let Some(index) = remove {
+        rules.remove(index);
+    }
+}
+
+/// Renders the counters of the triggers and the bookmarked lines, `revealable` tells whether a
+/// bookmark is still in the terminal
+pub fn trigger_status_ui(
+    ui_root: &mut egui::Ui,
+    counts: &BTreeMap<String, u64>,
+    bookmarks: &[Bookmark],
+    revealable: impl Fn(&Bookmark) -> bool
+) -> Option<TriggerStatusAction> {
+    let mut action = None;
+    if !counts.is_empty() {
+        ui_root.horizontal_wrapped(|ui_row| {
+            ui_row.label(egui::RichText::new("Triggers").strong());
+            for (name, count) in counts {
+                ui_row.label(egui::RichText::new(format!("{}: {}", name, count)).monospace());
+                ui_row.separator();
+            }
+            if ui_row.button("Reset").on_hover_text("Set the counters back to 0").clicked() {
+                action = Some(TriggerStatusAction::ResetCounts);
+            }
+        });
+    }
+    if bookmarks.is_empty() {
+        return action;
+    }
+    ui_root.horizontal(|ui_row| {
+        ui_row.label(egui::RichText::new(format!("Bookmarks ({})", bookmarks.len())).strong());
+        if ui_row.button("Clear").clicked() {
+            action = Some(TriggerStatusAction::ClearBookmarks);
+        }
+    });
+    egui::ScrollArea
+        ::vertical()
+        .id_source("bookmarks_scroll_area")
+        .max_height(100.0)
+        .stick_to_bottom(true)
+        .show(ui_root, |ui_scroll_area| {
+            for (index, bookmark) in bookmarks.iter().enumerate() {
+                ui_scroll_area.horizontal(|ui_row| {
+                    let show = ui_row
+                        .add_enabled(revealable(bookmark), egui::Button::new("Show").small())
+                        .on_disabled_hover_text("No longer in the terminal");
+                    if show.clicked() {
+                        action = Some(TriggerStatusAction::Reveal(index));
+                    }
+                    ui_row.label(
+                        egui::RichText
+                            ::new(
+                                format!(
+                                    "{} {}: {}",
+                                    bookmark.at.format("%H:%M:%S%.3f"),
+                                    bookmark.name,
+                                    bookmark.text
+                                )
+                            )
+                            .monospace()
+                    );
+                });
+            }
+        });
+    action
+}
@@ -0,0 +1,515 @@
+//! ANSI escape sequences, as sent by shells and colour logging of firmware. SGR sequences
+//! become text styles, cursor movement and erasing within a line edit the line being
+//! received, anything else (screen clearing, cursor positioning, titles) is dropped since the
+//! terminal is a scrollback, not a screen.
+
+use std::ops::Range;
+
+use crate::models::highlight::{ Rgb, Style };
+use crate::models::scrollback::MAX_LINE_LEN;
+
+/// Last column the cursor moves to, so a line stays within `MAX_LINE_LEN`
+const LAST_COLUMN: usize = MAX_LINE_LEN - 1;
+
+/// What a character of the input does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Print(char),
+    /// Select Graphic Rendition, the parameters of `ESC[...m`
+    Sgr(Vec<u16>),
+    /// `ESC[K`, 0 to the end of the line, 1 to the cursor, 2 the whole line
+    EraseLine(u16),
+    CursorBack(usize),
+    CursorForward(usize),
+    /// `ESC[nG`, counted from 0
+    CursorColumn(usize),
+    CarriageReturn,
+    SaveCursor,
+    RestoreCursor,
+    /// Part of a sequence, or a sequence without meaning in a scrollback
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    /// `ESC(` and friends, one more character names the character set
+    Charset,
+    Csi,
+    /// Operating system command, up to BEL or `ESC\`
+    Osc,
+    OscEscape,
+}
+
+/// Splits text into printable characters and the sequences between them, sequences may span
+/// several calls
+#[derive(Debug, Clone, Default)]
+pub struct AnsiParser {
+    state: State,
+    params: String,
+}
+
+impl AnsiParser {
+    pub fn new() -> AnsiParser {
+        AnsiParser::default()
+    }
+
+    pub fn feed(&mut self, c: char) -> Action {
+        match self.state {
+            State::Ground => {
+                match c {
+                    '\x1b' => {
+                        self.state = State::Escape;
+                        Action::None
+                    }
+                    '\r' => Action::CarriageReturn,
+                    '\x08' => Action::CursorBack(1),
+                    '\x07' => Action::None,
+                    c => Action::Print(c),
+                }
+            }
+            State::Escape => {
+                self.state = State::Ground;
+                match c {
+                    '[' => {
+                        self.params.clear();
+                        self.state = State::Csi;
+                        Action::None
+                    }
+                    ']' => {
+                        self.state = State::Osc;
+                        Action::None
+                    }
+                    '(' | ')' | '*' | '+' | '#' => {
+                        self.state = State::Charset;
+                        Action::None
+                    }
+                    '7' => Action::SaveCursor,
+                    '8' => Action::RestoreCursor,
+                    _ => Action::None,
+                }
+            }
+            State::Charset => {
+                self.state = State::Ground;
+                Action::None
+            }
+            State::Csi => {
+                match c {
+                    '\x20'..='\x3f' => {
+                        self.params.push(c);
+                        Action::None
+                    }
+                    '\x40'..='\x7e' => {
+                        self.state = State::Ground;
+                        self.csi(c)
+                    }
+                    // not a sequence after all
+                    _ => {
+                        self.state = State::Ground;
+                        Action::None
+                    }
+                }
+            }
+            State::Osc => {
+                match c {
+                    '\x07' => {
+                        self.state = State::Ground;
+                    }
+                    '\x1b' => {
+                        self.state = State::OscEscape;
+                    }
+                    _ => {}
+                }
+                Action::None
+            }
+            State::OscEscape => {
+                self.state = if c == '\\' { State::Ground } else { State::Osc };
+                Action::None
+            }
+        }
+    }
+
+    fn csi(&self, command: char) -> Action {
+        // private sequences such as `ESC[?25l` mean nothing here
+        if self.params.starts_with(['?', '<', '=', '>']) {
+            return Action::None;
+        }
+        let params: Vec<u16> = self.params
+            .split([';', ':'])
+            .filter(|_| !self.params.is_empty())
+            .map(|param| param.parse().unwrap_or(0))
+            .collect();
+        let count = usize::from(params.first().copied().unwrap_or(1).max(1));
+        match command {
+            'm' => Action::Sgr(params),
+            'K' => Action::EraseLine(params.first().copied().unwrap_or(0)),
+            'D' => Action::CursorBack(count),
+            'C' => Action::CursorForward(count),
+            'G' => Action::CursorColumn(count - 1),
+            's' => Action::SaveCursor,
+            'u' => Action::RestoreCursor,
+            _ => Action::None,
+        }
+    }
+}
+
+/// The 16 colours of SGR 30-37 and 90-97
+const PALETTE: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xcd, 0x31, 0x31],
+    [0x0d, 0xbc, 0x79],
+    [0xe5, 0xe5, 0x10],
+    [0x24, 0x72, 0xc8],
+    [0xbc, 0x3f, 0xbc],
+    [0x11, 0xa8, 0xcd],
+    [0xe5, 0xe5, 0xe5],
+    [0x66, 0x66, 0x66],
+    [0xf1, 0x4c, 0x4c],
+    [0x23, 0xd1, 0x8b],
+    [0xf5, 0xf5, 0x43],
+    [0x3b, 0x8e, 0xea],
+    [0xd6, 0x70, 0xd6],
+    [0x29, 0xb8, 0xdb],
+    [0xff, 0xff, 0xff],
+];
+
+/// Colour `index` of the xterm 256 colour palette
+fn palette_256(index: u16) -> Rgb {
+    match index {
+        0..=15 => Rgb(PALETTE[usize::from(index)]),
+        16..=231 => {
+            let level = |value: u16| if value == 0 { 0 } else { (55 + value * 40) as u8 };
+            let index = index - 16;
+            Rgb([level(index / 36), level((index / 6) % 6), level(index % 6)])
+        }
+        _ => {
+            let gray = (8 + (index.min(255) - 232) * 10) as u8;
+            Rgb([gray, gray, gray])
+        }
+    }
+}
+
+/// The colour of `38;5;n` or `38;2;r;g;b` starting at `params[0]`, and the parameters it took
+fn extended_color(params: &[u16]) -> (Option<Rgb>, usize) {
+    let channel = |i: usize| params.get(i).map_or(0, |value| (*value).min(255) as u8);
+    match params.first() {
+        Some(5) => (params.get(1).map(|index| palette_256(*index)), 2),
+        Some(2) => (Some(Rgb([channel(1), channel(2), channel(3)])), 4),
+        _ => (None, params.len()),
+    }
+}
+
+/// Applies the parameters of an SGR sequence to `style`
+pub fn apply_sgr(style: &mut Style, params: &[u16]) {
+    if params.is_empty() {
+        *style = Style::default();
+        return;
+    }
+    let mut i = 0;
+    while i < params.len() {
+        match params[i] {
+            0 => {
+                *style = Style::default();
+            }
+            1 => {
+                style.bold = true;
+            }
+            3 => {
+                style.italic = true;
+            }
+            4 => {
+                style.underline = true;
+            }
+            7 => {
+                style.inverse = true;
+            }
+            22 => {
+                style.bold = false;
+            }
+            23 => {
+                style.italic = false;
+            }
+            24 => {
+                style.underline = false;
+            }
+            27 => {
+                style.inverse = false;
+            }
+            code @ 30..=37 => {
+                style.foreground = Some(Rgb(PALETTE[usize::from(code - 30)]));
+            }
+            code @ 40..=47 => {
+                style.background = Some(Rgb(PALETTE[usize::from(code - 40)]));
+            }
+            code @ 90..=97 => {
+                style.foreground = Some(Rgb(PALETTE[usize::from(code - 90 + 8)]));
+            }
+            code @ 100..=107 => {
+                style.background = Some(Rgb(PALETTE[usize::from(code - 100 + 8)]));
+            }
+            38 => {
+                let (color, taken) = extended_color(&params[i + 1..]);
+                style.foreground = color.or(style.foreground);
+                i += taken;
+            }
+            48 => {
+                let (color, taken) = extended_color(&params[i + 1..]);
+                style.background = color.or(style.background);
+                i += taken;
+            }
+            39 => {
+                style.foreground = None;
+            }
+            49 => {
+                style.background = None;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// The SGR sequence that sets `style` from the default one
+pub fn sgr(style: &Style) -> String {
+    let mut sequence = String::from("\x1b[0");
+    for (set, code) in [
+        (style.bold, ";1"),
+        (style.italic, ";3"),
+        (style.underline, ";4"),
+        (style.inverse, ";7"),
+    ] {
+        if set {
+            sequence.push_str(code);
+        }
+    }
+    if let Some(Rgb([r, g, b])) = style.foreground {
+        sequence.push_str(&format!(";38;2;{};{};{}", r, g, b));
+    }
+    if let Some(Rgb([r, g, b])) = style.background {
+        sequence.push_str(&format!(";48;2;{};{};{}", r, g, b));
+    }
+    sequence.push('m');
+    sequence
+}
+
+/// `text` without its escape sequences, each line starting in the default style
+pub fn styled(text: &str) -> (String, Vec<(Range<usize>, Style)>) {
+    let mut parser = AnsiParser::new();
+    let mut style = Style::default();
+    let mut plain = String::with_capacity(text.len());
+    let mut spans: Vec<(Range<usize>, Style)> = Vec::new();
+    for c in text.chars() {
+        match parser.feed(c) {
+            Action::Print(c) => {
+                let start = plain.len();
+                plain.push(c);
+                match spans.last_mut() {
+                    Some((range, last)) if *last == style => {
+                        range.end = plain.len();
+                    }
+                    _ => spans.push((start..plain.len(), style)),
+                }
+            }
+            Action::Sgr(params) => apply_sgr(&mut style, &params),
+            _ => {}
+        }
+    }
+    (plain, spans)
+}
+
+/// `text` without its escape sequences
+pub fn strip(text: &str) -> String {
+    if !text.contains('\x1b') {
+        return text.to_string();
+    }
+    styled(text).0
+}
+
+/// The line being received as a terminal would show it, the cursor moving over it and
+/// erasing parts of it
+#[derive(Debug, Clone, Default)]
+pub struct LineEditor {
+    parser: AnsiParser,
+    /// Style of the next character printed, it carries over to the next line
+    style: Style,
+    cells: Vec<(char, Style)>,
+    cursor: usize,
+    saved_cursor: usize,
+    /// Cells already written out to the view
+    written: usize,
+    /// Style of the last cell written out
+    written_style: Style,
+    /// A cell already written out changed, the line has to be written out again
+    edited: bool,
+}
+
+impl LineEditor {
+    pub fn new() -> LineEditor {
+        LineEditor::default()
+    }
+
+    pub fn feed(&mut self, c: char) {
+        match self.parser.feed(c) {
+            Action::Print(c) => {
+                self.pad_to_cursor();
+                if self.cursor < self.cells.len() {
+                    self.cells[self.cursor] = (c, self.style);
+                    self.edited |= self.cursor < self.written;
+                } else {
+                    self.cells.push((c, self.style));
+                }
+                self.cursor += 1;
+            }
+            Action::Sgr(params) => apply_sgr(&mut self.style, &params),
+            Action::EraseLine(mode) => {
+                let (range, truncate) = match mode {
+                    0 => (self.cursor.min(self.cells.len())..self.cells.len(), true),
+                    1 => (0..(self.cursor + 1).min(self.cells.len()), false),
+                    _ => (0..self.cells.len(), true),
+                };
+                if range.start < self.written && !range.is_empty() {
+                    self.edited = true;
+                }
+                if truncate {
+                    self.cells.truncate(range.start);
+                } else {
+                    for cell in &mut self.cells[range] {
+                        *cell = (' ', Style::default());
+                    }
+                }
+            }
+            Action::CursorBack(count) => {
+                self.cursor = self.cursor.saturating_sub(count);
+            }
+            Action::CursorForward(count) => {
+                self.cursor = self.cursor.saturating_add(count).min(LAST_COLUMN);
+            }
+            Action::CursorColumn(column) => {
+                self.cursor = column.min(LAST_COLUMN);
+            }
+            Action::CarriageReturn => {
+                self.cursor = 0;
+            }
+            Action::SaveCursor => {
+                self.saved_cursor = self.cursor;
+            }
+            Action::RestoreCursor => {
+                self.cursor = self.saved_cursor.min(LAST_COLUMN);
+            }
+            Action::None => {}
+        }
+    }
+
+    fn pad_to_cursor(&mut self) {
+        while self.cells.len() < self.cursor {
+            self.cells.push((' ', Style::default()));
+        }
+    }
+
+    /// Writes the line out to `view`, where it starts at `start`. Printed characters are
+    /// appended, an edited line is written again.
+    pub fn write(&mut self, view: &mut String, start: usize) {
+        if self.edited || self.written > self.cells.len() {
+            if view.is_char_boundary(start) {
+                view.truncate(start);
+            }
+            self.written = 0;
+            self.written_style = Style::default();
+            self.edited = false;
+        }
+        for &(c, style) in &self.cells[self.written..] {
+            if style != self.written_style {
+                view.push_str(&sgr(&style));
+                self.written_style = style;
+            }
+            view.push(c);
+        }
+        self.written = self.cells.len();
+    }
+
+    /// The characters of the line without their styles
+    pub fn text(&self) -> String {
+        self.cells
+            .iter()
+            .map(|&(c, _)| c)
+            .collect()
+    }
+
+    /// Number of characters on the line, including the padding the cursor moved over
+    pub fn width(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Starts a new line, the style stays
+    pub fn new_line(&mut self) {
+        self.cells.clear();
+        self.cursor = 0;
+        self.saved_cursor = 0;
+        self.written = 0;
+        self.written_style = Style::default();
+        self.edited = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn edited(input: &str) -> String {
+        let mut editor = LineEditor::new();
+        let mut view = String::from("> ");
+        for c in input.chars() {
+            editor.feed(c);
+            // written out after every character, as if each came in its own chunk
+            editor.write(&mut view, 2);
+        }
+        view
+    }
+
+    #[test]
+    fn sgr_styles() {
+        let (text, spans) = styled("\x1b[1;31mERR\x1b[0m ok \x1b[38;5;46mgo\x1b[39;44m!\x1b[m");
+        assert_eq!(text, "ERR ok go!");
+        let red = Style { foreground: Some(Rgb(PALETTE[1])), bold: true, ..Default::default() };
+        let green = Style { foreground: Some(Rgb([0, 0xff, 0])), ..Default::default() };
+        let blue = Style { background: Some(Rgb(PALETTE[4])), ..Default::default() };
+        assert_eq!(
+            spans,
+            vec![(0..3, red), (3..7, Style::default()), (7..9, green), (9..10, blue)]
+        );
+
+        let mut style = Style::default();
+        apply_sgr(&mut style, &[38, 2, 1, 2, 3, 4, 7]);
+        let expected = Style {
+            foreground: Some(Rgb([1, 2, 3])),
+            underline: true,
+            inverse: true,
+            ..Default::default()
+        };
+        assert_eq!(style, expected);
+        assert_eq!(styled(&sgr(&style)).1, vec![]);
+        assert_eq!(styled(&format!("{}x", sgr(&style))).1, vec![(0..1, expected)]);
+    }
+
+    #[test]
+    fn other_sequences_are_dropped() {
+        let screen = "\x1b[2J\x1b[1;1H\x1b[?25lU-Boot\x1b]0;title\x07 \x1b(B2024";
+        assert_eq!(strip(screen), "U-Boot 2024");
+        assert_eq!(strip("\x1b]8;;http://x\x1b\\link"), "link");
+    }
+
+    #[test]
+    fn line_editing() {
+        // a shell redrawing its prompt and the command being typed
+        assert_eq!(edited("uart:~$ kernel\x08\x08\x08\x1b[Kbug"), "> uart:~$ kerbug");
+        assert_eq!(edited("progress 10%\rprogress 100%"), "> progress 100%");
+        assert_eq!(edited("abcdef\x1b[3D\x1b[1K"), ">     ef");
+        assert_eq!(edited("ab\x1b[3Cc"), "> ab   c");
+        assert_eq!(
+            edited("\x1b[32mok\x1b[0m\x1b7 wait\x1b8\x1b[K"),
+            "> \x1b[0;38;2;13;188;121mok"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Raw session capture, every chunk received or sent with its arrival time and direction,
+//! and replay of a capture into the terminal as if it came from the port.
+//!
+//! The file starts with `CAPTURE_MAGIC`, followed by one record per chunk, all integers
+//! little endian:
+//!
+//! | bytes | field                                        |
+//! |-------|----------------------------------------------|
+//! | 8     | arrival time, microseconds since Unix epoch  |
+//! | 1     | direction, 0 received, 1 sent                |
+//! | 4     | length of the chunk                          |
+//! | n     | the bytes of the chunk                       |
+
+use std::fs::File;
+use std::io::{ BufReader, BufWriter, Read, Write };
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, Instant };
+
+use chrono::{ DateTime, Local };
+
+use crate::models::scrollback::{ Chunk, Direction };
+
+/// Start of every capture file, the last byte is the format version
+pub const CAPTURE_MAGIC: &[u8; 8] = b"ARRAKIS\x01";
+
+/// Extension of capture files
+pub const CAPTURE_EXTENSION: &str = "arrcap";
+
+/// Longest chunk of a capture, longer ones are written as several records. A longer length in
+/// a file means it is corrupt, rather than something to allocate.
+pub const MAX_CHUNK_LEN: usize = 1 << 24;
+
+/// Chunks handed out by one poll when replaying as fast as possible, so the UI keeps drawing
+pub const MAX_REPLAY_CHUNKS_PER_POLL: usize = 1024;
+
+pub struct CaptureWriter {
+    pub path: PathBuf,
+    file: BufWriter<File>,
+    pub chunks_written: usize,
+    pub bytes_written: usize,
+}
+
+impl CaptureWriter {
+    /// Creates the capture at `path`, an existing file is never overwritten
+    pub fn create(path: &Path) -> Result<CaptureWriter, String> {
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| format!("Failed to create capture {}: {}", path.display(), e))?;
+        let mut file = BufWriter::new(file);
+        file.write_all(CAPTURE_MAGIC).map_err(|e| e.to_string())?;
+        Ok(CaptureWriter {
+            path: path.to_path_buf(),
+            file,
+            chunks_written: 0,
+            bytes_written: 0,
+        })
+    }
+
+    /// Records `chunk`, messages of the application are not part of the session
+    pub fn write(&mut self, chunk: &Chunk) -> Result<(), String> {
+        let direction: u8 = match chunk.direction {
+            Direction::Rx => 0,
+            Direction::Tx => 1,
+            Direction::Local => {
+                return Ok(());
+            }
+        };
+        for bytes in chunk.bytes.chunks(MAX_CHUNK_LEN) {
+            let mut record = Vec::with_capacity(13 + bytes.len());
+            record.extend_from_slice(&chunk.at.timestamp_micros().to_le_bytes());
+            record.push(direction);
+            record.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            record.extend_from_slice(bytes);
+            self.file.write_all(&record).map_err(|e| e.to_string())?;
+        }
+        self.chunks_written += 1;
+        self.bytes_written += chunk.bytes.len();
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf, String> {
+        self.file.flush().map_err(|e| e.to_string())?;
+        Ok(self.path)
+    }
+}
+
+/// Reads every chunk of a capture
+pub fn read_capture(reader: impl Read) -> Result<Vec<Chunk>, String> {
+    let mut reader = BufReader::new(reader);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).map_err(|_| "Not a capture file".to_string())?;
+    if &magic != CAPTURE_MAGIC {
+        return Err("Not a capture file".to_string());
+    }
+
+    let mut chunks = Vec::new();
+    loop {
+        let mut head = [0u8; 13];
+        match reader.read_exact(&mut head[..1]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => {
+                return Err(e.to_string());
+            }
+        }
+        let truncated = |_| format!("Capture truncated after {} chunks", chunks.len());
+        reader.read_exact(&mut head[1..]).map_err(truncated)?;
+
+        let micros = i64::from_le_bytes(head[0..8].try_into().unwrap());
+        let at = DateTime::from_timestamp_micros(micros)
+            .ok_or_else(|| format!("Invalid time in chunk {}", chunks.len()))?
+            .with_timezone(&Local);
+        let direction = match head[8] {
+            0 => Direction::Rx,
+            1 => Direction::Tx,
+            other => {
+                return Err(format!("Invalid direction {} in chunk {}", other, chunks.len()));
+            }
+        };
+        let len = u32::from_le_bytes(head[9..13].try_into().unwrap()) as usize;
+        if len > MAX_CHUNK_LEN {
+            return Err(format!("Invalid length {} of chunk {}", len, chunks.len()));
+        }
+        let mut bytes = vec![0u8; len];
+        reader.read_exact(&mut bytes).map_err(truncated)?;
+        chunks.push(Chunk { at, direction, bytes });
+    }
+    Ok(chunks)
+}
+
+/// How fast a capture is replayed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReplaySpeed {
+    /// With the gaps between chunks as they were captured
+    #[default]
+    Original,
+    /// Gaps divided by the factor, 2.0 replays twice as fast
+    Scaled(f64),
+    AsFastAsPossible,
+}
+
+pub const REPLAY_SPEEDS: [ReplaySpeed; 6] = [
+    ReplaySpeed::Scaled(0.5),
+    ReplaySpeed::Original,
+    ReplaySpeed::Scaled(2.0),
+    ReplaySpeed::Scaled(10.0),
+    ReplaySpeed::Scaled(100.0),
+    ReplaySpeed::AsFastAsPossible,
+];
+
+impl ReplaySpeed {
+    pub fn label(&self) -> String {
+        match self {
+            ReplaySpeed::Original => "Original".to_string(),
+            ReplaySpeed::Scaled(factor) => format!("×{}", factor),
+            ReplaySpeed::AsFastAsPossible => "Max".to_string(),
+        }
+    }
+}
+
+/// A capture being fed back, chunks keep the time they were captured at
+pub struct Replay {
+    pub path: PathBuf,
+    chunks: Vec<Chunk>,
+    next: usize,
+    speed: ReplaySpeed,
+    started: Instant,
+}
+
+impl Replay {
+    pub fn open(path: &Path, speed: ReplaySpeed) -> Result<Replay, String> {
+        let file = File::open(path).map_err(|e|
+            format!("Failed to open capture {}: {}", path.display(), e)
+        )?;
+        let chunks = read_capture(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Replay::new(path, chunks, speed, Instant::now()))
+    }
+
+    pub fn new(path: &Path, chunks: Vec<Chunk>, speed: ReplaySpeed, started: Instant) -> Replay {
+        Replay { path: path.to_path_buf(), chunks, next: 0, speed, started }
+    }
+
+    /// When the first chunk was captured, the replayed session was connected then
+    pub fn origin(&self) -> Option<DateTime<Local>> {
+        self.chunks.first().map(|chunk| chunk.at)
+    }
+
+    /// Chunks replayed and the total
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.chunks.len())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.chunks.len()
+    }
+
+    /// The chunks due at `now`
+    pub fn poll(&mut self, now: Instant) -> Vec<Chunk> {
+        let elapsed = now.saturating_duration_since(self.started);
+        let mut due = Vec::new();
+        while let Some(chunk) = self.chunks.get(self.next) {
+            match self.due_in(chunk, elapsed) {
+                Some(wait) if !wait.is_zero() => {
+                    break;
+                }
+                None if due.len() >= MAX_REPLAY_CHUNKS_PER_POLL => {
+                    break;
+                }
+                _ => {}
+            }
+            due.push(chunk.clone());
+            self.next += 1;
+        }
+        due
+    }
+
+    /// How long after `now` the next chunk is due, to schedule the next poll
+    pub fn next_in(&self, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.started);
+        let chunk = self.chunks.get(self.next)?;
+        Some(self.due_in(chunk, elapsed).unwrap_or_default())
+    }
+
+    /// Time left until `chunk` is due, `None` when replaying as fast as possible
+    fn due_in(&self, chunk: &Chunk, elapsed: Duration) -> Option<Duration> {
+        let origin = self.origin()?;
+        let offset = (chunk.at - origin).to_std().unwrap_or_default();
+        let offset = match self.speed {
+            ReplaySpeed::Original => offset,
+            ReplaySpeed::Scaled(factor) => offset.div_f64(factor.max(f64::EPSILON)),
+            ReplaySpeed::AsFastAsPossible => {
+                return None;
+            }
+        };
+        Some(offset.saturating_sub(elapsed))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chunk(micros: i64, direction: Direction, bytes: &[u8]) -> Chunk {
+        let at = DateTime::from_timestamp_micros(1_767_225_600_000_000 + micros).unwrap();
+        Chunk { at: at.with_timezone(&Local), direction, bytes: bytes.to_vec() }
+    }
+
+    #[test]
+    fn capture_round_trip() {
+        let dir = std::env::temp_dir().join(format!("arrakis-capture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.arrcap");
+
+        let chunks = vec![
+            chunk(0, Direction::Rx, b"boot\r\n"),
+            chunk(1_500, Direction::Tx, b"go\n"),
+            chunk(2_000_001, Direction::Rx, &[0x00, 0xFF, 0x1B])
+        ];
+        let mut capture = CaptureWriter::create(&path).unwrap();
+        for chunk in &chunks {
+            capture.write(chunk).unwrap();
+        }
+        capture.write(&Chunk::new(Direction::Local, b"note".to_vec())).unwrap();
+        assert!(CaptureWriter::create(&path).is_err());
+        assert_eq!((capture.chunks_written, capture.bytes_written), (3, 12));
+        capture.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(read_capture(data.as_slice()).unwrap(), chunks);
+        let truncated = read_capture(&data[..data.len() - 1]);
+        assert_eq!(truncated, Err("Capture truncated after 2 chunks".to_string()));
+        assert!(read_capture(&b"ARRAKIS\x02"[..]).is_err());
+        let mut corrupt = data[..8 + 13].to_vec();
+        corrupt[8 + 9..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            read_capture(corrupt.as_slice()),
+            Err(format!("Invalid length {} of chunk 0", u32::MAX))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replay_speeds() {
+        let chunks = vec![
+            chunk(0, Direction::Rx, b"a"),
+            chunk(100_000, Direction::Rx, b"b"),
+            chunk(1_000_000, Direction::Rx, b"c")
+        ];
+        let start = Instant::now();
+        let path = Path::new("session.arrcap");
+
+        let mut replay = Replay::new(path, chunks.clone(), ReplaySpeed::Original, start);
+        assert_eq!(replay.poll(start).len(), 1);
+        assert_eq!(replay.next_in(start), Some(Duration::from_millis(100)));
+        assert_eq!(replay.poll(start + Duration::from_millis(500)).len(), 1);
+        assert!(!replay.is_finished());
+        assert_eq!(replay.poll(start + Duration::from_secs(1)).len(), 1);
+        assert!(replay.is_finished());
+
+        let mut replay = Replay::new(path, chunks.clone(), ReplaySpeed::Scaled(10.0), start);
+        assert_eq!(replay.poll(start + Duration::from_millis(50)).len(), 2);
+        assert_eq!(replay.progress(), (2, 3));
+
+        let mut replay = Replay::new(path, chunks, ReplaySpeed::AsFastAsPossible, start);
+        assert_eq!(replay.poll(start).len(), 3);
+    }
+}
@@ -0,0 +1,311 @@
+//! Terminal command line, the text typed after `/` in the transmit bar split into a command
+//! name, arguments and options by a `logos` lexer, i.e.
+//! `/send "reg 0x10" 0x1F --repeat=3 -q`
+
+use logos::Logos;
+
+#[derive(Logos, Debug, Clone, PartialEq)]
+#[logos(skip r"[ \t\r\n\f]+")]
+pub enum Token {
+    /// `--name`, `-n` or `--name=value`, the value lexed again as a token of its own
+    #[regex(
+        r#"--?[A-Za-z][A-Za-z0-9_-]*(=("([^"\\]|\\.)*"|[^ \t\r\n\f"]*))?"#,
+        |lex| lex.slice().to_string()
+    )]
+    Option(String),
+    #[regex(r"0[xX][0-9a-fA-F]+", |lex| u64::from_str_radix(&lex.slice()[2..], 16).ok())]
+    Hex(u64),
+    #[regex(r"-?[0-9]+", |lex| lex.slice().parse::<i64>().ok(), priority = 3)]
+    Integer(i64),
+    #[regex(r"-?[0-9]+\.[0-9]*([eE][-+]?[0-9]+)?", |lex| lex.slice().parse::<f64>().ok())]
+    #[regex(r"-?[0-9]+[eE][-+]?[0-9]+", |lex| lex.slice().parse::<f64>().ok())]
+    Float(f64),
+    #[regex(r#""([^"\\]|\\.)*""#, |lex| unescape(lex.slice()))]
+    Quoted(Vec<u8>),
+    /// Anything else up to the next space or quote
+    #[regex(r#"[^ \t\r\n\f"]+"#, |lex| lex.slice().to_string(), priority = 1)]
+    Word(String),
+}
+
+/// The bytes of a quoted string, `\n`, `\r`, `\t`, `\0`, `\\`, `\"` and `\xNN` escapes
+/// replaced, `\xNN` giving the byte itself and taking exactly two hex digits
+fn unescape(quoted: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut chars = quoted[1..quoted.len() - 1].chars();
+    let mut buffer = [0u8; 4];
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        match chars.next()? {
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            '0' => bytes.push(0),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+                bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+            }
+            other => bytes.extend_from_slice(other.encode_utf8(&mut buffer).as_bytes()),
+        }
+    }
+    Some(bytes)
+}
+
+/// The value of an argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Word(String),
+    /// A quoted string, escapes already replaced
+    Text(Vec<u8>),
+    Integer(i64),
+    Hex(u64),
+    Float(f64),
+}
+
+/// An argument or option value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub value: Value,
+    /// The argument as typed, quotes and escapes included
+    pub source: String,
+}
+
+impl Arg {
+    /// The argument for display and for commands taking a name, numbers formatted again
+    pub fn text(&self) -> String {
+        match &self.value {
+            Value::Word(word) => word.clone(),
+            Value::Text(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            Value::Integer(integer) => integer.to_string(),
+            Value::Hex(hex) => format!("0x{:X}", hex),
+            Value::Float(float) => float.to_string(),
+        }
+    }
+
+    /// The bytes to send for the argument, a quoted string exactly as its escapes give them and
+    /// anything else as typed
+    pub fn bytes(&self) -> Vec<u8> {
+        match &self.value {
+            Value::Text(bytes) => bytes.clone(),
+            _ => self.source.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self.value {
+            Value::Integer(integer) => Some(integer),
+            Value::Hex(hex) => i64::try_from(hex).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self.value {
+            Value::Float(float) => Some(float),
+            _ => self.as_integer().map(|integer| integer as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandLine {
+    pub name: String,
+    pub args: Vec<Arg>,
+    /// Options without their dashes, with their value if they were given one
+    pub options: Vec<(String, Option<Arg>)>,
+}
+
+impl CommandLine {
+    /// Whether `--name` or `-name` was given
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    /// The value of `--name=value`
+    pub fn option(&self, name: &str) -> Option<&Arg> {
+        self.options
+            .iter()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_ref())
+    }
+}
+
+/// What a line typed in the transmit bar is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input<'a> {
+    /// The command after a leading `/`
+    Command(&'a str),
+    /// Text for the port, a leading `//` is sent as one `/`
+    Send(&'a str),
+}
+
+pub fn classify(line: &str) -> Input<'_> {
+    if line.starts_with("//") {
+        return Input::Send(&line[1..]);
+    }
+    match line.strip_prefix('/') {
+        Some(command) => Input::Command(command),
+        None => Input::Send(line),
+    }
+}
+
+/// Parses one command line, without its leading `/`
+pub fn parse_command(input: &str) -> Result<CommandLine, String> {
+    let mut lexer = Token::lexer(input);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+        let column = lexer.span().start + 1;
+        match token {
+            Ok(token) => tokens.push((token, lexer.slice(), column)),
+            Err(()) if lexer.slice().starts_with('"') => {
+                return Err(format!("Unterminated or invalid string at column {}", column));
+            }
+            Err(()) => {
+                return Err(format!("Invalid '{}' at column {}", lexer.slice(), column));
+            }
+        }
+    }
+
+    let mut tokens = tokens.into_iter();
+    let name = match tokens.next() {
+        Some((Token::Word(name), _, _)) => name,
+        Some((_, _, column)) => {
+            return Err(format!("Expected a command name at column {}", column));
+        }
+        None => {
+            return Err("Empty command, try /help".to_string());
+        }
+    };
+
+    let mut line = CommandLine { name, args: Vec::new(), options: Vec::new() };
+    for (token, source, column) in tokens {
+        let arg = match token {
+            Token::Option(option) => {
+                let option = option.trim_start_matches('-');
+                match option.split_once('=') {
+                    Some((name, value)) => {
+                        let mut value_lexer = Token::lexer(value);
+                        let value = match (value_lexer.next(), value_lexer.next()) {
+                            (Some(Ok(token)), None) => to_arg(token, value),
+                            (None, _) => {
+                                return Err(
+                                    format!("Missing value for --{} at column {}", name, column)
+                                );
+                            }
+                            _ => {
+                                return Err(
+                                    format!("Invalid value for --{} at column {}", name, column)
+                                );
+                            }
+                        };
+                        line.options.push((name.to_string(), Some(value)));
+                    }
+                    None => line.options.push((option.to_string(), None)),
+                }
+                continue;
+            }
+            token => to_arg(token, source),
+        };
+        line.args.push(arg);
+    }
+    Ok(line)
+}
+
+fn to_arg(token: Token, source: &str) -> Arg {
+    let value = match token {
+        Token::Word(word) => Value::Word(word),
+        Token::Quoted(text) => Value::Text(text),
+        Token::Integer(integer) => Value::Integer(integer),
+        Token::Hex(hex) => Value::Hex(hex),
+        Token::Float(float) => Value::Float(float),
+        Token::Option(option) => Value::Word(option),
+    };
+    Arg { value, source: source.to_string() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arguments_and_options() {
+        let input = r#"send "reg 0x10\r\n" 0x1F -7 2.5e3 ok --repeat=3 -q --name="a b""#;
+        let line = parse_command(input).unwrap();
+        assert_eq!(line.name, "send");
+        assert_eq!(
+            line.args
+                .iter()
+                .map(|arg| arg.value.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                Value::Text(b"reg 0x10\r\n".to_vec()),
+                Value::Hex(0x1f),
+                Value::Integer(-7),
+                Value::Float(2500.0),
+                Value::Word("ok".to_string())
+            ]
+        );
+        assert_eq!(line.option("repeat").map(|arg| &arg.value), Some(&Value::Integer(3)));
+        let name = line.option("name").unwrap();
+        assert_eq!(name.value, Value::Text(b"a b".to_vec()));
+        assert_eq!(name.source, r#""a b""#);
+        assert!(line.flag("q"));
+        assert!(!line.flag("v"));
+        assert_eq!(line.args[1].text(), "0x1F");
+        assert_eq!(line.args[1].as_integer(), Some(31));
+    }
+
+    #[test]
+    fn arguments_keep_their_source() {
+        let line = parse_command("send w 0x1f 007 2.50 1e3").unwrap();
+        let sources: Vec<_> = line.args
+            .iter()
+            .map(|arg| String::from_utf8(arg.bytes()).unwrap())
+            .collect();
+        assert_eq!(sources, ["w", "0x1f", "007", "2.50", "1e3"]);
+        assert_eq!(line.args[1].text(), "0x1F");
+        assert_eq!(line.args[3].as_float(), Some(2.5));
+    }
+
+    #[test]
+    fn quoted_bytes() {
+        let line = parse_command(r#"send "\xFF\x80µ\"\\" --raw"#).unwrap();
+        assert_eq!(line.args[0].bytes(), vec![0xFF, 0x80, 0xC2, 0xB5, b'"', b'\\']);
+        assert_eq!(line.args[0].text(), "\u{FFFD}\u{FFFD}µ\"\\");
+        for invalid in [r#"send "\xG0""#, r#"send "\xF""#, r#"send "\xF ""#, r#"send "\x+1""#] {
+            assert_eq!(
+                parse_command(invalid).map(|_| ()),
+                Err("Unterminated or invalid string at column 6".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn classify_lines() {
+        assert_eq!(classify("/baud 9600"), Input::Command("baud 9600"));
+        assert_eq!(classify("//etc/motd"), Input::Send("/etc/motd"));
+        assert_eq!(classify("AT+GMR"), Input::Send("AT+GMR"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_command("  "), Err("Empty command, try /help".to_string()));
+        assert_eq!(
+            parse_command(r#"send "open"#),
+            Err("Unterminated or invalid string at column 6".to_string())
+        );
+        assert_eq!(
+            parse_command("0x10 send"),
+            Err("Expected a command name at column 1".to_string())
+        );
+        assert_eq!(
+            parse_command("baud --rate="),
+            Err("Missing value for --rate at column 6".to_string())
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Command registry, every terminal command declares its name, aliases, arguments, options
+//! and description. The registry checks a parsed command line against that before running
+//! it, and generates `/help` from it.
+
+use crate::models::command::{ Arg, CommandLine };
+
+/// What an argument accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// Anything, numbers are taken as typed
+    Text,
+    /// A decimal or hex integer
+    Integer,
+    /// Any number
+    Number,
+    /// One of the listed words
+    Choice(&'static [&'static str]),
+    /// A command name
+    Command,
+    /// A serial port name
+    Port,
+    /// A file path
+    Path,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+    /// Takes all remaining arguments, only for the last one
+    pub rest: bool,
+    pub description: &'static str,
+}
+
+impl ArgSpec {
+    pub fn required(
+        name: &'static str,
+        kind: ArgKind,
+        description: &'static str
+    ) -> ArgSpec {
+        ArgSpec { name, kind, required: true, rest: false, description }
+    }
+
+    pub fn optional(
+        name: &'static str,
+        kind: ArgKind,
+        description: &'static str
+    ) -> ArgSpec {
+        ArgSpec { name, kind, required: false, rest: false, description }
+    }
+
+    /// One or more arguments taking the rest of the line
+    pub fn rest(name: &'static str, description: &'static str) -> ArgSpec {
+        ArgSpec { name, kind: ArgKind::Text, required: true, rest: true, description }
+    }
+
+    fn usage(&self) -> String {
+        let name = match self.kind {
+            ArgKind::Choice(choices) => choices.join("|"),
+            _ => self.name.to_string(),
+        };
+        match (self.required, self.rest) {
+            (_, true) => format!("<{}>...", name),
+            (true, false) => format!("<{}>", name),
+            (false, false) => format!("[{}]", name),
+        }
+    }
+
+    fn check(&self, arg: &Arg) -> Result<(), String> {
+        let ok = match self.kind {
+            ArgKind::Text | ArgKind::Command | ArgKind::Port | ArgKind::Path => true,
+            ArgKind::Integer => arg.as_integer().is_some(),
+            ArgKind::Number => arg.as_float().is_some(),
+            ArgKind::Choice(choices) => choices.contains(&arg.text().as_str()),
+        };
+        if ok {
+            return Ok(());
+        }
+        match self.kind {
+            ArgKind::Choice(choices) => {
+                let choices = choices.join(", ");
+                Err(format!("<{}> is one of {}, not '{}'", self.name, choices, arg.text()))
+            }
+            _ => Err(format!("<{}> must be a number, not '{}'", self.name, arg.text())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    /// Given as `--name=value` rather than `--name`
+    pub takes_value: bool,
+    pub description: &'static str,
+}
+
+/// A command running on the context `C`, the application
+pub struct Command<C> {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: Vec<ArgSpec>,
+    pub options: Vec<OptionSpec>,
+    pub description: &'static str,
+    pub run: fn(&mut C, &CommandLine) -> Result<(), String>,
+}
+
+impl<C> Command<C> {
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in &self.args {
+            usage.push(' ');
+            usage.push_str(&arg.usage());
+        }
+        usage
+    }
+
+    /// Checks the arguments and options of `line` against the declaration
+    pub fn check(&self, line: &CommandLine) -> Result<(), String> {
+        let required = self.args.iter().filter(|arg| arg.required).count();
+        let rest = self.args.last().is_some_and(|arg| arg.rest);
+        if line.args.len() < required || (!rest && line.args.len() > self.args.len()) {
+            return Err(format!("usage: {}", self.usage()));
+        }
+        for (i, arg) in line.args.iter().enumerate() {
+            let spec = self.args.get(i).or(self.args.last()).unwrap();
+            spec.check(arg)?;
+        }
+        for (name, value) in &line.options {
+            let Some(spec) = self.options.iter().find(|option| option.name == name) else {
+                return Err(format!("unknown option --{}, see /help {}", name, self.name));
+            };
+            if spec.takes_value != value.is_some() {
+                return Err(
+                    if spec.takes_value {
+                        format!("--{} needs a value, as in --{}=...", name, name)
+                    } else {
+                        format!("--{} takes no value", name)
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The long help, `/help <command>`
+    pub fn help(&self) -> String {
+        let mut help = format!("{}\n  {}\n", self.usage(), self.description);
+        if !self.aliases.is_empty() {
+            help.push_str(&format!("  aliases: {}\n", self.aliases.join(", ")));
+        }
+        for arg in &self.args {
+            help.push_str(&format!("  {:<16} {}\n", arg.usage(), arg.description));
+        }
+        for option in &self.options {
+            let usage = if option.takes_value {
+                format!("--{}=...", option.name)
+            } else {
+                format!("--{}", option.name)
+            };
+            help.push_str(&format!("  {:<16} {}\n", usage, option.description));
+        }
+        help
+    }
+}
+
+pub struct CommandRegistry<C> {
+    commands: Vec<Command<C>>,
+}
+
+impl<C> Default for CommandRegistry<C> {
+    fn default() -> Self {
+        Self { commands: Vec::new() }
+    }
+}
+
+impl<C> CommandRegistry<C> {
+    pub fn new() -> CommandRegistry<C> {
+        CommandRegistry::default()
+    }
+
+    /// Adds a command, a later command with the same name or alias replaces the earlier one
+    pub fn register(&mut self, command: Command<C>) {
+        self.commands.retain(|existing| {
+            existing.name != command.name &&
+                !command.aliases.contains(&existing.name) &&
+                !existing.aliases.contains(&command.name)
+        });
+        self.commands.push(command);
+    }
+
+    pub fn commands(&self) -> &[Command<C>] {
+        &self.commands
+    }
+
+    /// The command called `name` or with `name` as an alias
+    pub fn find(&self, name: &str) -> Option<&Command<C>> {
+        self.commands
+            .iter()
+            .find(|command| command.name == name || command.aliases.contains(&name))
+    }
+
+    /// Runs `line` on `context` once it matches the declaration of its command
+    pub fn run(&self, context: &mut C, line: &CommandLine) -> Result<(), String> {
+        let Some(command) = self.find(&line.name) else {
+            return Err(format!("Unknown command '{}', try /help", line.name));
+        };
+        command.check(line)?;
+        (command.run)(context, line)
+    }
+
+    /// The short help, one line per command
+    pub fn help(&self) -> String {
+        let width = self.commands
+            .iter()
+            .map(|command| command.usage().chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut help = String::from("Commands:\n");
+        for command in &self.commands {
+            help.push_str(&format!("  {:<width$}  {}\n", command.usage(), command.description));
+        }
+        help.push_str("Type /help <command> for details\n");
+        help
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::command::parse_command;
+
+    const MODES: &[&str] = &["text", "hex"];
+
+    fn registry() -> CommandRegistry<Vec<String>> {
+        let mut registry = CommandRegistry::<Vec<String>>::new();
+        registry.register(Command {
+            name: "baud",
+            aliases: &["b"],
+            args: vec![ArgSpec::required("rate", ArgKind::Integer, "Baud rate")],
+            options: Vec::new(),
+            description: "Set the baud rate",
+            run: |calls, line| {
+                calls.push(format!("baud {}", line.args[0].text()));
+                Ok(())
+            },
+        });
+        registry.register(Command {
+            name: "send",
+            aliases: &[],
+            args: vec![
+                ArgSpec::optional("mode", ArgKind::Choice(MODES), "How to send"),
+                ArgSpec::rest("text", "What to send"),
+            ],
+            options: vec![
+                OptionSpec { name: "raw", takes_value: false, description: "No line end" }
+            ],
+            description: "Send a line",
+            run: |calls, line| {
+                calls.push(format!("send {}", line.args.len()));
+                Ok(())
+            },
+        });
+        registry
+    }
+
+    fn run(registry: &CommandRegistry<Vec<String>>, input: &str) -> Result<Vec<String>, String> {
+        let mut calls = Vec::new();
+        registry.run(&mut calls, &parse_command(input)?)?;
+        Ok(calls)
+    }
+
+    #[test]
+    fn arguments_are_checked() {
+        let registry = registry();
+        assert_eq!(run(&registry, "b 0x1C200"), Ok(vec!["baud 0x1C200".to_string()]));
+        assert_eq!(run(&registry, "baud"), Err("usage: /baud <rate>".to_string()));
+        assert_eq!(
+            run(&registry, "baud fast"),
+            Err("<rate> must be a number, not 'fast'".to_string())
+        );
+        assert_eq!(run(&registry, "send hex a b --raw"), Ok(vec!["send 3".to_string()]));
+        assert_eq!(
+            run(&registry, "send bin a"),
+            Err("<mode> is one of text, hex, not 'bin'".to_string())
+        );
+        assert!(run(&registry, "send text a --raw=1").is_err());
+        assert!(run(&registry, "send text a --quiet").is_err());
+        assert_eq!(run(&registry, "exit"), Err("Unknown command 'exit', try /help".to_string()));
+    }
+
+    #[test]
+    fn help_is_generated() {
+        let registry = registry();
+        assert_eq!(
+            registry.help(),
+            "Commands:\n\
+             \x20 /baud <rate>                Set the baud rate\n\
+             \x20 /send [text|hex] <text>...  Send a line\n\
+             Type /help <command> for details\n"
+        );
+        let help = registry.find("b").unwrap().help();
+        assert!(help.starts_with("/baud <rate>\n  Set the baud rate\n  aliases: b\n"));
+    }
+}
@@ -0,0 +1,269 @@
+//! Tab completion of the transmit bar, command names, their arguments and options, port names
+//! and file paths, from the declarations of the command registry
+
+use std::path::Path;
+
+use logos::Logos;
+
+use crate::models::command::{ classify, Input, Token };
+use crate::models::command_registry::{ ArgKind, CommandRegistry };
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+    /// Byte offset in the input of the word being completed
+    pub start: usize,
+    /// What the word can become, sorted
+    pub candidates: Vec<String>,
+}
+
+impl Completion {
+    /// The input with the word completed as far as all candidates agree, `None` when that
+    /// adds nothing and the candidates should be shown instead
+    pub fn apply(&self, input: &str) -> Option<String> {
+        let word = &input[self.start..];
+        let completed = match self.candidates.as_slice() {
+            [] => {
+                return None;
+            }
+            [only] if only.ends_with('/') || only.ends_with('=') => only.clone(),
+            [only] => format!("{} ", only),
+            candidates => common_prefix(candidates),
+        };
+        if completed == word {
+            return None;
+        }
+        Some(format!("{}{}", &input[..self.start], completed))
+    }
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let first = &candidates[0];
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, c), _)| i + c.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+/// Start of the word the cursor is in at the end of `input`, a quoted word starts at its quote
+fn word_start(input: &str) -> usize {
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' if quoted => quoted = false,
+            '"' => {
+                quoted = true;
+                start = i;
+            }
+            ' ' | '\t' if !quoted => start = i + 1,
+            _ => {}
+        }
+    }
+    start
+}
+
+/// What the word at the end of `input` can become, `input` being the whole transmit line
+pub fn complete<C>(registry: &CommandRegistry<C>, input: &str, ports: &[String]) -> Completion {
+    let Input::Command(body) = classify(input) else {
+        return Completion::default();
+    };
+    let start = word_start(body);
+    let word = &body[start..];
+    let mut completion = Completion { start: start + 1, candidates: Vec::new() };
+
+    let mut tokens = Token::lexer(&body[..start]).filter_map(Result::ok);
+    let Some(name) = tokens.next() else {
+        completion.candidates = registry
+            .commands()
+            .iter()
+            .flat_map(|command| std::iter::once(&command.name).chain(command.aliases))
+            .filter(|name| name.starts_with(word))
+            .map(|name| name.to_string())
+            .collect();
+        completion.candidates.sort();
+        return completion;
+    };
+    let Token::Word(name) = name else {
+        return completion;
+    };
+    let Some(command) = registry.find(&name) else {
+        return completion;
+    };
+
+    if word.starts_with('-') {
+        completion.candidates = command.options
+            .iter()
+            .map(|option| {
+                if option.takes_value {
+                    format!("--{}=", option.name)
+                } else {
+                    format!("--{}", option.name)
+                }
+            })
+            .filter(|option| option.starts_with(word))
+            .collect();
+        completion.candidates.sort();
+        return completion;
+    }
+
+    let index = tokens.filter(|token| !matches!(token, Token::Option(_))).count();
+    let spec = match command.args.get(index) {
+        Some(spec) => spec,
+        None => {
+            match command.args.last() {
+                Some(spec) if spec.rest => spec,
+                _ => {
+                    return completion;
+                }
+            }
+        }
+    };
+    completion.candidates = match spec.kind {
+        ArgKind::Choice(choices) => {
+            choices
+                .iter()
+                .filter(|choice| choice.starts_with(word))
+                .map(|choice| choice.to_string())
+                .collect()
+        }
+        ArgKind::Command => {
+            registry
+                .commands()
+                .iter()
+                .map(|command| command.name)
+                .filter(|name| name.starts_with(word))
+                .map(|name| name.to_string())
+                .collect()
+        }
+        ArgKind::Port => {
+            ports
+                .iter()
+                .filter(|port| port.starts_with(word))
+                .cloned()
+                .collect()
+        }
+        ArgKind::Path => complete_path(word),
+        ArgKind::Text | ArgKind::Integer | ArgKind::Number => Vec::new(),
+    };
+    completion.candidates.sort();
+    completion
+}
+
+/// Entries of the directory of `word` starting with its file name, directories ending in `/`,
+/// quoted when they hold a space
+fn complete_path(word: &str) -> Vec<String> {
+    let quoted = word.starts_with('"');
+    let word = word.trim_start_matches('"');
+    let (dir, prefix) = match word.rfind('/') {
+        Some(slash) => (&word[..=slash], &word[slash + 1..]),
+        None => ("", word),
+    };
+    let read_from = if dir.is_empty() { Path::new(".") } else { Path::new(dir) };
+    let Ok(entries) = std::fs::read_dir(read_from) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            let path = format!("{}{}", dir, name);
+            Some(match (is_dir, quoted || path.contains(' ')) {
+                (true, false) => format!("{}/", path),
+                (true, true) => format!("\"{}/", path),
+                (false, false) => path,
+                (false, true) => format!("\"{}\"", path),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::command_registry::{ ArgSpec, Command, OptionSpec };
+
+    const ACTIONS: &[&str] = &["start", "stop"];
+
+    fn registry() -> CommandRegistry<()> {
+        let mut registry = CommandRegistry::<()>::new();
+        let commands = [
+            ("connect", ArgSpec::required("port", ArgKind::Port, "")),
+            ("capture", ArgSpec::required("action", ArgKind::Choice(ACTIONS), "")),
+            ("replay", ArgSpec::optional("file", ArgKind::Path, "")),
+        ];
+        for (name, arg) in commands {
+            registry.register(Command {
+                name,
+                aliases: &[],
+                args: vec![arg],
+                options: vec![
+                    OptionSpec { name: "speed", takes_value: true, description: "" },
+                    OptionSpec { name: "quiet", takes_value: false, description: "" }
+                ],
+                description: "",
+                run: |_, _| Ok(()),
+            });
+        }
+        registry
+    }
+
+    fn completed(input: &str) -> Option<String> {
+        let ports = ["/dev/ttyUSB0".to_string(), "/dev/ttyACM0".to_string()];
+        complete(&registry(), input, &ports).apply(input)
+    }
+
+    #[test]
+    fn commands_arguments_and_ports() {
+        assert_eq!(completed("/cap"), Some("/capture ".to_string()));
+        assert_eq!(completed("/c"), None);
+        assert_eq!(complete(&registry(), "/c", &[]).candidates, ["capture", "connect"]);
+        assert_eq!(completed("/capture s"), Some("/capture st".to_string()));
+        assert_eq!(completed("/capture st"), None);
+        assert_eq!(completed("/capture sta"), Some("/capture start ".to_string()));
+        assert_eq!(completed("/capture start s"), None);
+        assert_eq!(completed("/connect /dev/ttyU"), Some("/connect /dev/ttyUSB0 ".to_string()));
+        assert_eq!(
+            completed("/connect --quiet /dev/ttyA"),
+            Some("/connect --quiet /dev/ttyACM0 ".to_string())
+        );
+        assert_eq!(completed("/connect --s"), Some("/connect --speed=".to_string()));
+        assert_eq!(completed("w 0x1"), None);
+    }
+
+    #[test]
+    fn paths() {
+        let dir = std::env::temp_dir().join(format!("arrakis-completion-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("captures")).unwrap();
+        std::fs::write(dir.join("bench run.arrcap"), b"").unwrap();
+        let dir = dir.display().to_string();
+
+        assert_eq!(
+            completed(&format!("/replay {}/ca", dir)),
+            Some(format!("/replay {}/captures/", dir))
+        );
+        assert_eq!(
+            completed(&format!("/replay {}/b", dir)),
+            Some(format!("/replay \"{}/bench run.arrcap\" ", dir))
+        );
+        assert_eq!(
+            completed(&format!("/replay \"{}/be", dir)),
+            Some(format!("/replay \"{}/bench run.arrcap\" ", dir))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,360 @@
+//! Connection lifecycle, Disconnected → Connecting → Connected → Closing, with one reader per
+//! connection that can be cancelled so the OS port or socket is released
+
+use std::io::Read;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ channel, Receiver, Sender };
+use std::sync::{ Arc, Mutex };
+
+use chrono::{ DateTime, Local };
+
+use crate::models::scrollback::{ Chunk, Direction };
+use crate::models::serial_config::SerialConfig;
+use crate::models::transport::{ Endpoint, Transport };
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConnectionState {
+    #[default]
+    Disconnected,
+    /// The port is being opened, or a TCP server is waiting for its client
+    Connecting,
+    /// The port is open and the reader is running
+    Connected,
+    /// The reader has been asked to stop and the port will be released once it has
+    Closing,
+}
+
+impl ConnectionState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConnectionState::Disconnected => "Disconnected",
+            ConnectionState::Connecting => "Connecting",
+            ConnectionState::Connected => "Connected",
+            ConnectionState::Closing => "Closing",
+        }
+    }
+}
+
+/// Size of the chunks handed from the reader thread to the UI, a read returns as soon as
+/// any bytes are available so most chunks are much smaller
+pub const READ_CHUNK_SIZE: usize = 4096;
+
+/// What the connection reports to the UI on each poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Received {
+    /// Bytes read from the port, stamped by the reader as they arrived
+    Data(Chunk),
+    /// The port was opened at this time, the origin of timestamps since connect
+    Connected(DateTime<Local>),
+    /// A change in the connection, for the terminal
+    Status(String),
+}
+
+/// Messages from the opener and reader threads back to the connection
+enum Event {
+    Opened(Box<dyn Transport>, DateTime<Local>),
+    OpenFailed(String),
+    Data(Chunk),
+    /// The reader has exited and dropped its handle on the port, with the error that
+    /// stopped it unless it was cancelled
+    ReaderStopped(Option<String>),
+}
+
+pub struct Connection {
+    pub state: ConnectionState,
+    pub endpoint: Endpoint,
+    /// Serial port name or endpoint URL, as shown to the user
+    pub port_name: String,
+    pub config: SerialConfig,
+    /// Levels last asked of the control lines, ports open with both raised
+    pub dtr: bool,
+    pub rts: bool,
+    /// Writer side of the port, the reader works on its own clone
+    port: Option<Arc<Mutex<Box<dyn Transport>>>>,
+    /// Set to ask the reader to exit, checked at least once per read timeout
+    stop: Arc<AtomicBool>,
+    events_tx: Sender<Event>,
+    events_rx: Receiver<Event>,
+    /// Called from the background threads whenever there is something to poll
+    notify: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl Connection {
+    pub fn new(notify: impl Fn() + Send + Sync + 'static) -> Connection {
+        let (events_tx, events_rx) = channel();
+        Connection {
+            state: ConnectionState::Disconnected,
+            endpoint: Endpoint::default(),
+            port_name: String::new(),
+            config: SerialConfig::new(),
+            dtr: true,
+            rts: true,
+            port: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            events_tx,
+            events_rx,
+            notify: Arc::new(notify),
+        }
+    }
+
+    /// The port the connection is using, if it is not disconnected
+    pub fn active_port(&self) -> Option<&str> {
+        match self.state {
+            ConnectionState::Disconnected => None,
+            _ => Some(&self.port_name),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state == ConnectionState::Connected
+    }
+
+    /// The open port, for writing
+    pub fn port(&self) -> Option<&Arc<Mutex<Box<dyn Transport>>>> {
+        self.port.as_ref()
+    }
+
+    /// Changes the line settings of the open port
+    pub fn apply_config(&mut self, config: &SerialConfig) -> Result<(), String> {
+        if let Some(port) = self.port.as_ref() {
+            port.lock().unwrap().set_config(config)?;
+        }
+        self.config = config.clone();
+        Ok(())
+    }
+
+    pub fn set_dtr(&mut self, on: bool) -> Result<(), String> {
+        let port = self.port.as_ref().ok_or("Not connected")?;
+        port.lock().unwrap().set_dtr(on)?;
+        self.dtr = on;
+        Ok(())
+    }
+
+    pub fn set_rts(&mut self, on: bool) -> Result<(), String> {
+        let port = self.port.as_ref().ok_or("Not connected")?;
+        port.lock().unwrap().set_rts(on)?;
+        self.rts = on;
+        Ok(())
+    }
+
+    /// Starts opening `endpoint`, only possible while disconnected
+    pub fn connect(&mut self, endpoint: &Endpoint, config: &SerialConfig) -> Result<(), String> {
+        if self.state != ConnectionState::Disconnected {
+            return Err(
+                format!("Already {} to {}", self.state.label().to_lowercase(), self.port_name)
+            );
+        }
+        self.state = ConnectionState::Connecting;
+        self.endpoint = endpoint.clone();
+        self.port_name = endpoint.to_string();
+        self.config = config.clone();
+        self.dtr = true;
+        self.rts = true;
+        // a fresh flag, so a reader of an earlier connection can never be revived
+        self.stop = Arc::new(AtomicBool::new(false));
+
+        let endpoint = endpoint.clone();
+        let config = config.clone();
+        let stop = Arc::clone(&self.stop);
+        let events = self.events_tx.clone();
+        let notify = Arc::clone(&self.notify);
+        tokio::task::spawn_blocking(move || {
+            let event = match endpoint.open(&config, &stop) {
+                Ok(port) => Event::Opened(port, Local::now()),
+                Err(err) => Event::OpenFailed(err),
+            };
+            let _ = events.send(event);
+            notify();
+        });
+        Ok(())
+    }
+
+    /// Asks the reader to stop, the port is released once it has
+    pub fn disconnect(&mut self) {
+        match self.state {
+            ConnectionState::Connecting | ConnectionState::Connected => {
+                self.stop.store(true, Ordering::SeqCst);
+                self.state = ConnectionState::Closing;
+            }
+            ConnectionState::Disconnected | ConnectionState::Closing => {}
+        }
+    }
+
+    /// Advances the lifecycle with whatever the background threads reported, returning the
+    /// data read from the port and status messages in the order they happened
+    pub fn poll(&mut self) -> Vec<Received> {
+        let mut received = Vec::new();
+        while let Ok(event) = self.events_rx.try_recv() {
+            match event {
+                Event::Data(chunk) => received.push(Received::Data(chunk)),
+                Event::Opened(port, opened_at) => {
+                    if self.stop.load(Ordering::SeqCst) {
+                        // disconnected while the port was being opened, the task never
+                        // started so dropping the port closes it
+                        drop(port);
+                        self.state = ConnectionState::Disconnected;
+                        received.push(
+                            Received::Status(format!("Disconnected from {}", self.port_name))
+                        );
+                        continue;
+                    }
+                    match port.try_clone() {
+                        Ok(reader_port) => {
+                            self.port = Some(Arc::new(Mutex::new(port)));
+                            self.state = ConnectionState::Connected;
+                            if let Err(e) = self.spawn_reader(reader_port) {
+                                self.port = None;
+                                self.state = ConnectionState::Disconnected;
+                                received.push(
+                                    Received::Status(format!("Failed to start reader: {}", e))
+                                );
+                                continue;
+                            }
+                            received.push(Received::Connected(opened_at));
+                            let message = if self.endpoint.has_line_settings() {
+                                format!(
+                                    "Connected to {} at {}",
+                                    self.port_name,
+                                    self.config.summary()
+                                )
+                            } else {
+                                format!("Connected to {}", self.port_name)
+                            };
+                            received.push(Received::Status(message));
+                        }
+                        Err(e) => {
+                            self.state = ConnectionState::Disconnected;
+                            let message = format!("Failed to open {}: {}", self.port_name, e);
+                            received.push(Received::Status(message));
+                        }
+                    }
+                }
+                Event::OpenFailed(err) => {
+                    self.state = ConnectionState::Disconnected;
+                    received.push(Received::Status(err));
+                }
+                Event::ReaderStopped(err) => {
+                    // the last handle on the port goes here, so another tool can open it
+                    self.port = None;
+                    self.state = ConnectionState::Disconnected;
+                    if let Some(err) = err {
+                        let message = format!("Error reading from {}: {}", self.port_name, err);
+                        received.push(Received::Status(message));
+                    }
+                    received.push(
+                        Received::Status(format!("Disconnected from {}", self.port_name))
+                    );
+                }
+            }
+        }
+        received
+    }
+
+    /// Starts the reader on a dedicated thread, it blocks in `read` for at most the read
+    /// timeout, hands every chunk to the UI as soon as it arrives and exits when asked to stop
+    fn spawn_reader(&self, mut port: Box<dyn Transport>) -> std::io::Result<()> {
+        let stop = Arc::clone(&self.stop);
+        let events = self.events_tx.clone();
+        let notify = Arc::clone(&self.notify);
+        std::thread::Builder
+            ::new()
+            .name(format!("reader {}", self.port_name))
+            .spawn(move || {
+                let mut buffer = [0u8; READ_CHUNK_SIZE];
+                let mut error = None;
+                while !stop.load(Ordering::SeqCst) {
+                    match port.read(&mut buffer) {
+                        Ok(0) => {
+                            error = Some("end of stream".to_string());
+                            break;
+                        }
+                        Ok(count) => {
+                            let chunk = Chunk::new(Direction::Rx, buffer[..count].to_vec());
+                            let _ = events.send(Event::Data(chunk));
+                            notify();
+                        }
+                        // a quiet line is not an error, keep waiting for data
+                        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                        Err(e) => {
+                            error = Some(e.to_string());
+                            break;
+                        }
+                    }
+                }
+                drop(port);
+                let _ = events.send(Event::ReaderStopped(error));
+                notify();
+            })
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn failed_open_returns_to_disconnected() {
+        let mut connection = Connection::new(|| {});
+        let config = SerialConfig::new();
+
+        connection.connect(&Endpoint::serial("/dev/arrakis-no-such-port"), &config).unwrap();
+        assert_eq!(connection.state, ConnectionState::Connecting);
+        assert_eq!(connection.active_port(), Some("/dev/arrakis-no-such-port"));
+        // only one connection at a time
+        let other = Endpoint::serial("/dev/arrakis-other-port");
+        assert!(connection.connect(&other, &config).is_err());
+
+        let mut received = Vec::new();
+        while received.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            received = connection.poll();
+        }
+        match &received[0] {
+            Received::Status(message) => assert!(message.starts_with("Failed to open serial port")),
+            other => panic!("expected a failure, got {:?}", other),
+        }
+        assert_eq!(connection.state, ConnectionState::Disconnected);
+        assert!(connection.port().is_none());
+    }
+
+    #[tokio::test]
+    async fn tcp_round_trip() {
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = Endpoint::parse(
+            &format!("tcp://{}", listener.local_addr().unwrap())
+        ).unwrap();
+        let mut connection = Connection::new(|| {});
+        connection.connect(&endpoint, &SerialConfig::new()).unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+        peer.write_all(b"boot\r\n").unwrap();
+
+        let mut data = Vec::new();
+        let mut statuses = Vec::new();
+        while data.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            for received in connection.poll() {
+                match received {
+                    Received::Data(chunk) => data.extend(chunk.bytes),
+                    Received::Status(message) => statuses.push(message),
+                    Received::Connected(_) => {}
+                }
+            }
+        }
+        assert_eq!(data, b"boot\r\n");
+        assert_eq!(statuses, vec![format!("Connected to {}", endpoint)]);
+        connection.port().unwrap().lock().unwrap().write_all(b"help\n").unwrap();
+
+        // the peer going away ends the connection
+        drop(peer);
+        while connection.state != ConnectionState::Disconnected {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            connection.poll();
+        }
+        assert!(connection.port().is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! CSV data log, every line received or sent with the time it started, its direction, the
+//! raw line and the channels the parsers found in it as `name=value` fields
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+
+use chrono::Local;
+
+use crate::models::parsers::ParserSet;
+use crate::models::scrollback::{ Chunk, Direction, LineSplitter, TextLine };
+
+/// Columns before the parsed fields, which take as many columns as a line has
+pub const CSV_COLUMNS: [&str; 4] = ["timestamp", "direction", "line", "fields"];
+
+pub struct DataLog {
+    pub path: PathBuf,
+    writer: csv::Writer<File>,
+    lines: LineSplitter,
+    pub lines_written: usize,
+}
+
+impl DataLog {
+    /// Creates the log at `path` starting with `header` as `# key: value` comment lines,
+    /// an existing file is never overwritten
+    pub fn create(path: &Path, header: &[(String, String)]) -> Result<DataLog, String> {
+        let mut file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| format!("Failed to create log {}: {}", path.display(), e))?;
+
+        let mut block = String::from("# Arrakis data log\n");
+        for (key, value) in header {
+            // a multi-line value keeps every line commented
+            for line in value.lines().filter(|line| !line.trim().is_empty()) {
+                block.push_str(&format!("# {}: {}\n", key, line));
+            }
+        }
+        block.push_str(&format!("# started: {}\n", Local::now().to_rfc3339()));
+        file
+            .write_all(block.as_bytes())
+            .map_err(|e| format!("Failed to write log {}: {}", path.display(), e))?;
+
+        let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(file);
+        writer.write_record(CSV_COLUMNS).map_err(|e| e.to_string())?;
+        writer.flush().map_err(|e| e.to_string())?;
+
+        Ok(DataLog {
+            path: path.to_path_buf(),
+            writer,
+            lines: LineSplitter::new(),
+            lines_written: 0,
+        })
+    }
+
+    /// Ends lines at carriage returns not followed by a line feed
+    pub fn set_cr_newline(&mut self, cr_newline: bool) {
+        self.lines.set_cr_newline(cr_newline);
+    }
+
+    /// Logs the lines `chunk` completes, messages of the application are not logged
+    pub fn write(&mut self, chunk: &Chunk, parsers: &ParserSet) -> Result<(), String> {
+        for line in self.lines.push(chunk) {
+            self.write_line(&line, parsers)?;
+        }
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+
+    /// Logs the line still being received and closes the file
+    pub fn finish(mut self, parsers: &ParserSet) -> Result<PathBuf, String> {
+        if let Some(line) = self.lines.finish() {
+            self.write_line(&line, parsers)?;
+        }
+        self.writer.flush().map_err(|e| e.to_string())?;
+        Ok(self.path)
+    }
+
+    fn write_line(&mut self, line: &TextLine, parsers: &ParserSet) -> Result<(), String> {
+        let direction = if line.direction == Direction::Tx { "TX" } else { "RX" };
+        let timestamp = line.at.format("%Y-%m-%dT%H:%M:%S%.6f%:z").to_string();
+        let mut record = vec![timestamp, direction.to_string(), line.text.clone()];
+        record.extend(
+            parsers
+                .parse(&line.text)
+                .into_iter()
+                .map(|sample| format!("{}={}", sample.channel, sample.value))
+        );
+        self.writer.write_record(&record).map_err(|e| e.to_string())?;
+        self.lines_written += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lines_with_fields() {
+        let dir = std::env::temp_dir().join(format!("arrakis-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("RealData.csv");
+        let header = [
+            ("module".to_string(), "adc".to_string()),
+            ("description".to_string(), "first line\nsecond line".to_string()),
+        ];
+
+        let parsers = ParserSet::default();
+        let mut log = DataLog::create(&path, &header).unwrap();
+        // an existing log is never overwritten
+        assert!(DataLog::create(&path, &header).is_err());
+        log.write(&Chunk::new(Direction::Rx, b"temp=21.5, rpm".to_vec()), &parsers).unwrap();
+        // colour sequences are left out of the log
+        let coloured = Chunk::new(Direction::Rx, b" 1200\r\n\x1B[32mok\x1B[0m\n".to_vec());
+        log.write(&coloured, &parsers).unwrap();
+        log.write(&Chunk::new(Direction::Local, b"note\n".to_vec()), &parsers).unwrap();
+        log.write(&Chunk::new(Direction::Tx, b"go, \"now\"".to_vec()), &parsers).unwrap();
+        assert_eq!(log.lines_written, 2);
+        log.finish(&parsers).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "# Arrakis data log");
+        assert_eq!(lines[1], "# module: adc");
+        assert_eq!(lines[3], "# description: second line");
+        assert!(lines[4].starts_with("# started: "));
+        assert_eq!(lines[5], "timestamp,direction,line,fields");
+        assert!(lines[6].ends_with(",RX,\"temp=21.5, rpm 1200\",temp=21.5,rpm=1200"));
+        assert!(lines[7].ends_with(",RX,ok"));
+        assert!(lines[8].ends_with(",TX,\"go, \"\"now\"\"\""));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! Highlight rules, regexes whose matches are coloured in the terminal, i.e. red for `ERROR`
+
+use std::ops::Range;
+
+use regex::Regex;
+use serde::{ Deserialize, Serialize };
+
+/// A colour, saved as `#rrggbb`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb(pub [u8; 3]);
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Rgb, String> {
+        let hex = text.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(format!("Invalid colour '{}', expected #rrggbb", text));
+        }
+        let mut rgb = [0; 3];
+        for (i, channel) in rgb.iter_mut().enumerate() {
+            *channel = u8
+                ::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| format!("Invalid colour '{}', expected #rrggbb", text))?;
+        }
+        Ok(Rgb(rgb))
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(rgb: Rgb) -> String {
+        format!("#{:02x}{:02x}{:02x}", rgb.0[0], rgb.0[1], rgb.0[2])
+    }
+}
+
+/// How text is drawn, by highlight rules or ANSI sequences, unset parts keep the terminal
+/// colours
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub foreground: Option<Rgb>,
+    pub background: Option<Rgb>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// Foreground and background swapped
+    pub inverse: bool,
+}
+
+impl Style {
+    /// `self` drawn over `below`
+    pub fn over(&self, below: &Style) -> Style {
+        Style {
+            foreground: self.foreground.or(below.foreground),
+            background: self.background.or(below.background),
+            bold: self.bold || below.bold,
+            italic: self.italic || below.italic,
+            underline: self.underline || below.underline,
+            inverse: self.inverse || below.inverse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighlightRule {
+    pub name: String,
+    pub enabled: bool,
+    pub pattern: String,
+    pub case_sensitive: bool,
+    pub foreground: Option<Rgb>,
+    pub background: Option<Rgb>,
+    pub bold: bool,
+}
+
+impl Default for HighlightRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            pattern: String::new(),
+            case_sensitive: true,
+            foreground: None,
+            background: None,
+            bold: false,
+        }
+    }
+}
+
+impl HighlightRule {
+    pub fn style(&self) -> Style {
+        Style {
+            foreground: self.foreground,
+            background: self.background,
+            bold: self.bold,
+            ..Default::default()
+        }
+    }
+}
+
+/// The rules a new project starts with
+pub fn default_rules() -> Vec<HighlightRule> {
+    vec![
+        HighlightRule {
+            name: "Errors".to_string(),
+            pattern: r"\b(ERROR|ERR|FATAL|PANIC)\b".to_string(),
+            foreground: Some(Rgb([0xff, 0x55, 0x55])),
+            bold: true,
+            ..Default::default()
+        },
+        HighlightRule {
+            name: "Warnings".to_string(),
+            pattern: r"\b(WARN|WARNING)\b".to_string(),
+            foreground: Some(Rgb([0xf1, 0xfa, 0x8c])),
+            ..Default::default()
+        },
+        HighlightRule {
+            name: "Addresses".to_string(),
+            pattern: r"\b0[xX][0-9a-fA-F]+\b".to_string(),
+            foreground: Some(Rgb([0x8b, 0xe9, 0xfd])),
+            ..Default::default()
+        }
+    ]
+}
+
+/// The enabled rules of the project, built once and applied to every line shown
+#[derive(Default)]
+pub struct Highlighter {
+    /// What the highlighter was built from, to notice edits
+    pub configs: Vec<HighlightRule>,
+    rules: Vec<(Regex, Style)>,
+    /// Rules whose pattern does not compile
+    pub errors: Vec<String>,
+}
+
+impl Highlighter {
+    pub fn new(configs: &[HighlightRule]) -> Highlighter {
+        let mut rules = Vec::new();
+        let mut errors = Vec::new();
+        for config in configs.iter().filter(|config| config.enabled && !config.pattern.is_empty()) {
+            let pattern = if config.case_sensitive {
+                config.pattern.clone()
+            } else {
+                format!("(?i){}", config.pattern)
+            };
+            match Regex::new(&pattern) {
+                Ok(regex) => rules.push((regex, config.style())),
+                Err(e) => errors.push(format!("Highlight {}: {}", config.name, e)),
+            }
+        }
+        Highlighter { configs: configs.to_vec(), rules, errors }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// `line` split into runs of one style, covering all of it, later rules drawn over earlier
+    /// ones
+    pub fn spans(&self, line: &str) -> Vec<(Range<usize>, Style)> {
+        if self.rules.is_empty() || line.is_empty() {
+            return vec![(0..line.len(), Style::default())];
+        }
+        let mut styles = vec![Style::default(); line.len()];
+        for (regex, style) in &self.rules {
+            for found in regex.find_iter(line) {
+                for byte in &mut styles[found.range()] {
+                    *byte = style.over(byte);
+                }
+            }
+        }
+        runs(line, styles)
+    }
+}
+
+/// The style of each byte of `line` as runs of one style, never splitting a character
+pub fn runs(line: &str, styles: Vec<Style>) -> Vec<(Range<usize>, Style)> {
+    let mut spans: Vec<(Range<usize>, Style)> = Vec::new();
+    for (i, style) in styles.into_iter().enumerate() {
+        match spans.last_mut() {
+            Some((range, last)) if *last == style || !line.is_char_boundary(i) => {
+                range.end = i + 1;
+            }
+            _ => spans.push((i..i + 1, style)),
+        }
+    }
+    spans
+}
+
+/// The runs of `above` drawn over the runs of `below`, both covering the same text
+pub fn overlay(
+    below: &[(Range<usize>, Style)],
+    above: &[(Range<usize>, Style)]
+) -> Vec<(Range<usize>, Style)> {
+    let mut spans = Vec::new();
+    let (mut b, mut a) = (below.iter().peekable(), above.iter().peekable());
+    let mut start = 0;
+    // the pieces are where either changes style
+    while let (Some((below_range, below_style)), Some((above_range, above_style))) =
+        (b.peek(), a.peek())
+    {
+        let end = below_range.end.min(above_range.end);
+        spans.push((start..end, above_style.over(below_style)));
+        start = end;
+        if below_range.end == end {
+            b.next();
+        }
+        if above_range.end == end {
+            a.next();
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn colour_round_trip() {
+        let rgb = Rgb::try_from("#FF5500".to_string()).unwrap();
+        assert_eq!(rgb, Rgb([0xff, 0x55, 0x00]));
+        assert_eq!(String::from(rgb), "#ff5500");
+        assert!(Rgb::try_from("red".to_string()).is_err());
+    }
+
+    #[test]
+    fn overlapping_rules() {
+        let highlighter = Highlighter::new(&default_rules());
+        assert!(highlighter.errors.is_empty());
+        let red = Some(Rgb([0xff, 0x55, 0x55]));
+        let cyan = Some(Rgb([0x8b, 0xe9, 0xfd]));
+        let line = "ERROR at 0x20001000 °C";
+        let spans = highlighter.spans(line);
+        assert_eq!(
+            spans,
+            vec![
+                (0..5, Style { foreground: red, bold: true, ..Default::default() }),
+                (5..9, Style::default()),
+                (9..19, Style { foreground: cyan, ..Default::default() }),
+                (19..line.len(), Style::default())
+            ]
+        );
+
+        let rules = [
+            HighlightRule {
+                pattern: "boot".to_string(),
+                case_sensitive: false,
+                bold: true,
+                ..Default::default()
+            },
+            HighlightRule {
+                name: "broken".to_string(),
+                pattern: "(".to_string(),
+                ..Default::default()
+            },
+        ];
+        let highlighter = Highlighter::new(&rules);
+        assert_eq!(highlighter.errors.len(), 1);
+        assert!(highlighter.errors[0].starts_with("Highlight broken:"));
+        assert_eq!(highlighter.spans("BOOT").len(), 1);
+        assert!(highlighter.spans("BOOT")[0].1.bold);
+    }
+
+    #[test]
+    fn overlay_runs() {
+        let bold = Style { bold: true, ..Default::default() };
+        let red = Style { foreground: Some(Rgb([0xff, 0, 0])), ..Default::default() };
+        let below = [(0..4, bold), (4..8, Style::default())];
+        let above = [(0..2, Style::default()), (2..6, red), (6..8, Style::default())];
+        assert_eq!(
+            overlay(&below, &above),
+            vec![
+                (0..2, bold),
+                (2..4, red.over(&bold)),
+                (4..6, red),
+                (6..8, Style::default())
+            ]
+        );
+    }
+}
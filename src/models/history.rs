@@ -0,0 +1,191 @@
+//! Transmit history, the lines and commands sent from the transmit bar, kept in a file per
+//! project so they survive restarts
+
+use std::fs::{ File, OpenOptions };
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+
+/// Name of the history file in the project directory
+pub const HISTORY_FILE_NAME: &str = "arrakis-history";
+/// Lines kept, older ones are dropped when the history is loaded
+pub const MAX_HISTORY: usize = 1000;
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest first
+    entries: Vec<String>,
+    /// File the entries are appended to, opened with the first new entry
+    path: Option<PathBuf>,
+    file: Option<File>,
+    /// Entry shown by Up/Down, `entries.len()` when back at the line being typed
+    cursor: usize,
+    /// The line being typed before browsing started
+    draft: String,
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// The history in `path`, an empty one if the file does not exist yet. Nothing is created
+    /// until a line is added.
+    pub fn load(path: &Path) -> Result<History, String> {
+        let mut entries: Vec<String> = match std::fs::read_to_string(path) {
+            Ok(text) => text.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(format!("Failed to read {}: {}", path.display(), e));
+            }
+        };
+        if entries.len() > MAX_HISTORY {
+            entries.drain(..entries.len() - MAX_HISTORY);
+            let mut text = entries.join("\n");
+            text.push('\n');
+            std::fs
+                ::write(path, text)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        Ok(History {
+            cursor: entries.len(),
+            entries,
+            path: Some(path.to_path_buf()),
+            file: None,
+            draft: String::new(),
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Adds a sent line, unless it repeats the last one, and stops browsing
+    pub fn push(&mut self, line: &str) -> Result<(), String> {
+        self.cursor = self.entries.len();
+        self.draft.clear();
+        if line.trim().is_empty() || line.contains('\n') {
+            return Ok(());
+        }
+        if self.entries.last().is_some_and(|last| last == line) {
+            return Ok(());
+        }
+        self.entries.push(line.to_string());
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+        self.cursor = self.entries.len();
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        if let Err(e) = self.append(&path, line) {
+            self.path = None;
+            self.file = None;
+            return Err(format!("Stopped saving the history: {}", e));
+        }
+        Ok(())
+    }
+
+    /// Writes `line` to the history file `path`, creating it on the first line
+    fn append(&mut self, path: &Path, line: &str) -> Result<(), String> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => {
+                if let Some(dir) = path.parent() {
+                    std::fs
+                        ::create_dir_all(dir)
+                        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+                }
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+                self.file.insert(file)
+            }
+        };
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    /// The entry before the one shown, `input` is kept to come back to
+    pub fn older(&mut self, input: &str) -> Option<&str> {
+        if self.cursor == 0 {
+            return None;
+        }
+        if self.cursor == self.entries.len() {
+            self.draft = input.to_string();
+        }
+        self.cursor -= 1;
+        Some(&self.entries[self.cursor])
+    }
+
+    /// The entry after the one shown, the line being typed after the newest
+    pub fn newer(&mut self) -> Option<&str> {
+        if self.cursor >= self.entries.len() {
+            return None;
+        }
+        self.cursor += 1;
+        match self.entries.get(self.cursor) {
+            Some(entry) => Some(entry),
+            None => Some(&self.draft),
+        }
+    }
+
+    /// The newest entry older than `before` containing `query`, with its index
+    pub fn search(&self, query: &str, before: usize) -> Option<(usize, &str)> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, entry)| entry.contains(query))
+            .map(|(index, entry)| (index, entry.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn browse_and_search() {
+        let mut history = History::new();
+        for line in ["/baud 9600", "w 0x10 0x1F", "w 0x10 0x1F", "r 0x10"] {
+            history.push(line).unwrap();
+        }
+        assert_eq!(history.entries().len(), 3);
+
+        assert_eq!(history.older("r 0x"), Some("r 0x10"));
+        assert_eq!(history.older(""), Some("w 0x10 0x1F"));
+        assert_eq!(history.older(""), Some("/baud 9600"));
+        assert_eq!(history.older(""), None);
+        assert_eq!(history.newer(), Some("w 0x10 0x1F"));
+        assert_eq!(history.newer(), Some("r 0x10"));
+        assert_eq!(history.newer(), Some("r 0x"));
+        assert_eq!(history.newer(), None);
+
+        assert_eq!(history.search("0x10", 3), Some((2, "r 0x10")));
+        assert_eq!(history.search("0x10", 2), Some((1, "w 0x10 0x1F")));
+        assert_eq!(history.search("baud", 0), None);
+    }
+
+    #[test]
+    fn saved_between_sessions() {
+        let dir = std::env::temp_dir().join(format!("arrakis-history-{}", std::process::id()));
+        let path = dir.join(HISTORY_FILE_NAME);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut history = History::load(&path).unwrap();
+        assert!(!dir.exists());
+        history.push("w 0x10 0x1F").unwrap();
+        history.push("/log start").unwrap();
+        drop(history);
+
+        let mut history = History::load(&path).unwrap();
+        assert_eq!(history.entries(), ["w 0x10 0x1F", "/log start"]);
+        assert_eq!(history.older(""), Some("/log start"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,26 @@
 
+pub mod ansi;
 pub mod banner;
+pub mod capture;
+pub mod command;
+pub mod command_registry;
+pub mod completion;
+pub mod connection;
+pub mod data_log;
 pub mod details; 
+pub mod highlight;
+pub mod history;
+pub mod parsers;
+pub mod plot;
+pub mod ports;
+pub mod profiles;
+pub mod rfc2217;
+pub mod project_file;
+pub mod scrollback;
+pub mod search;
+pub mod serial_config;
+pub mod transmit;
+pub mod transport;
+pub mod trigger;
 
 //pub use pins::Fpga;
\ No newline at end of file
@@ -0,0 +1,418 @@
+//! Line parsers, turning received lines into named channel samples for the plot and the
+//! data log. A parser splits delimited columns, picks `key=value` pairs or matches a regex
+//! with named capture groups, channels can carry a unit and a scale and offset.
+
+use regex::Regex;
+use serde::{ Deserialize, Serialize };
+
+/// How a parser finds the values in a line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParserKind {
+    /// Columns split by a delimiter, named by `columns`
+    #[default]
+    Delimited,
+    /// `key=value` pairs, named by their keys
+    KeyValue,
+    /// A regex, named by its capture groups
+    Regex,
+}
+
+pub const PARSER_KINDS: [ParserKind; 3] = [
+    ParserKind::Delimited,
+    ParserKind::KeyValue,
+    ParserKind::Regex,
+];
+
+impl ParserKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParserKind::Delimited => "Delimited",
+            ParserKind::KeyValue => "Key=Value",
+            ParserKind::Regex => "Regex",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Delimiter {
+    #[default]
+    Comma,
+    Tab,
+    /// Any run of whitespace
+    Space,
+    Semicolon,
+}
+
+pub const DELIMITERS: [Delimiter; 4] = [
+    Delimiter::Comma,
+    Delimiter::Tab,
+    Delimiter::Space,
+    Delimiter::Semicolon,
+];
+
+impl Delimiter {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Delimiter::Comma => "Comma",
+            Delimiter::Tab => "Tab",
+            Delimiter::Space => "Space",
+            Delimiter::Semicolon => "Semicolon",
+        }
+    }
+
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self {
+            Delimiter::Comma => line.split(',').collect(),
+            Delimiter::Tab => line.split('\t').collect(),
+            Delimiter::Space => line.split_whitespace().collect(),
+            Delimiter::Semicolon => line.split(';').collect(),
+        }
+    }
+}
+
+/// Unit and conversion of one channel, `value * scale + offset`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelConfig {
+    pub name: String,
+    pub unit: String,
+    pub scale: f64,
+    pub offset: f64,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self { name: String::new(), unit: String::new(), scale: 1.0, offset: 0.0 }
+    }
+}
+
+/// A parser as saved in the project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParserConfig {
+    pub name: String,
+    pub enabled: bool,
+    /// Only lines starting with this are parsed, without it, i.e. `$ADC,`
+    pub prefix: String,
+    pub kind: ParserKind,
+    /// For `Delimited`, the delimiter and the channel name of each column, a column without
+    /// a name is `#1`, `#2`, ...
+    pub delimiter: Delimiter,
+    pub columns: Vec<String>,
+    /// For `KeyValue`, what is between a key and its value
+    pub separator: String,
+    /// For `Regex`, every named group is a channel
+    pub pattern: String,
+    pub channels: Vec<ChannelConfig>,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            name: "Parser".to_string(),
+            enabled: true,
+            prefix: String::new(),
+            kind: ParserKind::default(),
+            delimiter: Delimiter::default(),
+            columns: Vec::new(),
+            separator: "=".to_string(),
+            pattern: String::new(),
+            channels: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// `text` as a number, `nan` and `inf` are not numbers a device measured
+fn parse_number(text: &str) -> Option<f64> {
+    text.parse::<f64>().ok().filter(|number| number.is_finite())
+}
+
+impl Value {
+    fn parse(text: &str) -> Value {
+        match parse_number(text.trim()) {
+            Some(number) => Value::Number(number),
+            None => Value::Text(text.trim().to_string()),
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Text(_) => None,
+        }
+    }
+}
+
+/// One value of a channel found in a line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub channel: String,
+    pub value: Value,
+    pub unit: String,
+}
+
+/// The numbers in `line` with their names, what is parsed when no parser is configured. A
+/// number is named by the word in front of it, as in `temp=21.5`, `vbat: 3.3` or `rpm 1200`,
+/// otherwise by its position, i.e. `#3`.
+pub fn named_values(line: &str) -> Vec<(String, f64)> {
+    let mut values = Vec::new();
+    let mut name: Option<&str> = None;
+    let tokens = line
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';'))
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        if let Some((key, value)) = token.split_once(['=', ':']) {
+            match parse_number(value) {
+                Some(value) if !key.is_empty() => values.push((key.to_string(), value)),
+                // `vbat: 3.3`, the value is the next token
+                _ if value.is_empty() && !key.is_empty() => {
+                    name = Some(key);
+                    continue;
+                }
+                _ => {}
+            }
+            name = None;
+        } else if let Some(value) = parse_number(token) {
+            let name = name
+                .take()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("#{}", values.len() + 1));
+            values.push((name, value));
+        } else {
+            name = Some(token);
+        }
+    }
+    values
+}
+
+/// A parser ready to run, its regex compiled
+struct LineParser {
+    config: ParserConfig,
+    regex: Option<Regex>,
+}
+
+impl LineParser {
+    fn new(config: &ParserConfig) -> Result<LineParser, String> {
+        let regex = match config.kind {
+            ParserKind::Regex => {
+                let regex = Regex::new(&config.pattern).map_err(|e|
+                    format!("Parser {}: {}", config.name, e)
+                )?;
+                if regex.capture_names().flatten().next().is_none() {
+                    return Err(
+                        format!(
+                            "Parser {}: the regex has no named groups like (?P<temp>...)",
+                            config.name
+                        )
+                    );
+                }
+                Some(regex)
+            }
+            _ => None,
+        };
+        Ok(LineParser { config: config.clone(), regex })
+    }
+
+    fn parse(&self, line: &str) -> Vec<Sample> {
+        let Some(line) = line.strip_prefix(self.config.prefix.as_str()) else {
+            return Vec::new();
+        };
+        let mut found: Vec<(String, Value)> = Vec::new();
+        match self.config.kind {
+            ParserKind::Delimited => {
+                for (i, field) in self.config.delimiter.split(line).into_iter().enumerate() {
+                    if field.trim().is_empty() {
+                        continue;
+                    }
+                    let name = match self.config.columns.get(i) {
+                        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+                        _ => format!("#{}", i + 1),
+                    };
+                    found.push((name, Value::parse(field)));
+                }
+            }
+            ParserKind::KeyValue => {
+                let separator = if self.config.separator.is_empty() {
+                    "="
+                } else {
+                    self.config.separator.as_str()
+                };
+                let tokens = line
+                    .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';'))
+                    .filter(|token| !token.is_empty());
+                for token in tokens {
+                    if let Some((key, value)) = token.split_once(separator) {
+                        if !key.is_empty() && !value.is_empty() {
+                            found.push((key.to_string(), Value::parse(value)));
+                        }
+                    }
+                }
+            }
+            ParserKind::Regex => {
+                let Some(regex) = &self.regex else {
+                    return Vec::new();
+                };
+                if let Some(captures) = regex.captures(line) {
+                    for name in regex.capture_names().flatten() {
+                        if let Some(group) = captures.name(name) {
+                            found.push((name.to_string(), Value::parse(group.as_str())));
+                        }
+                    }
+                }
+            }
+        }
+        found
+            .into_iter()
+            .map(|(channel, value)| self.sample(channel, value))
+            .collect()
+    }
+
+    /// Applies the channel's unit, scale and offset
+    fn sample(&self, channel: String, value: Value) -> Sample {
+        match self.config.channels.iter().find(|config| config.name == channel) {
+            Some(config) => {
+                let value = match value {
+                    Value::Number(number) => Value::Number(number * config.scale + config.offset),
+                    text => text,
+                };
+                Sample { channel, value, unit: config.unit.clone() }
+            }
+            None => Sample { channel, value, unit: String::new() },
+        }
+    }
+}
+
+/// The enabled parsers of the project, every one runs on every line
+#[derive(Default)]
+pub struct ParserSet {
+    /// What the set was built from, to notice edits
+    pub configs: Vec<ParserConfig>,
+    parsers: Vec<LineParser>,
+    /// Parsers that could not be built
+    pub errors: Vec<String>,
+}
+
+impl ParserSet {
+    pub fn new(configs: &[ParserConfig]) -> ParserSet {
+        let mut parsers = Vec::new();
+        let mut errors = Vec::new();
+        for config in configs.iter().filter(|config| config.enabled) {
+            match LineParser::new(config) {
+                Ok(parser) => parsers.push(parser),
+                Err(err) => errors.push(err),
+            }
+        }
+        ParserSet { configs: configs.to_vec(), parsers, errors }
+    }
+
+    /// The samples in `line`, without parsers the named numbers of `named_values`
+    pub fn parse(&self, line: &str) -> Vec<Sample> {
+        if self.configs.iter().all(|config| !config.enabled) {
+            return named_values(line)
+                .into_iter()
+                .map(|(channel, value)| Sample {
+                    channel,
+                    value: Value::Number(value),
+                    unit: String::new(),
+                })
+                .collect();
+        }
+        self.parsers
+            .iter()
+            .flat_map(|parser| parser.parse(line))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn values(samples: &[Sample]) -> Vec<String> {
+        samples
+            .iter()
+            .map(|sample| format!("{}={}{}", sample.channel, sample.value, sample.unit))
+            .collect()
+    }
+
+    #[test]
+    fn values_are_named() {
+        let values = named_values("adc0=512 vbat: 3.30, rpm 1200 -7.5 ok 1e3");
+        let values: Vec<(&str, f64)> = values
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![("adc0", 512.0), ("vbat", 3.3), ("rpm", 1200.0), ("#4", -7.5), ("ok", 1000.0)]
+        );
+        assert!(named_values("boot complete").is_empty());
+        assert!(named_values("temp=nan vbat: inf rpm -infinity NaN").is_empty());
+        assert_eq!(Value::parse(" inf "), Value::Text("inf".to_string()));
+        // without parsers the named numbers are parsed
+        assert_eq!(values_of(&ParserSet::new(&[]), "t=1"), vec!["t=1"]);
+    }
+
+    fn values_of(set: &ParserSet, line: &str) -> Vec<String> {
+        values(&set.parse(line))
+    }
+
+    #[test]
+    fn parser_kinds() {
+        let adc = ParserConfig {
+            name: "adc".to_string(),
+            prefix: "$ADC,".to_string(),
+            columns: vec!["ch0".to_string(), String::new(), "state".to_string()],
+            channels: vec![ChannelConfig {
+                name: "ch0".to_string(),
+                unit: "V".to_string(),
+                scale: 0.5,
+                offset: 1.0,
+            }],
+            ..Default::default()
+        };
+        let pairs = ParserConfig {
+            name: "pairs".to_string(),
+            kind: ParserKind::KeyValue,
+            separator: ":".to_string(),
+            ..Default::default()
+        };
+        let regex = ParserConfig {
+            name: "regex".to_string(),
+            kind: ParserKind::Regex,
+            pattern: r"took (?P<took>\d+) ms".to_string(),
+            ..Default::default()
+        };
+        let set = ParserSet::new(&[adc, pairs, regex]);
+        assert!(set.errors.is_empty());
+
+        assert_eq!(values_of(&set, "$ADC,4,7,idle"), vec!["ch0=3V", "#2=7", "state=idle"]);
+        assert_eq!(values_of(&set, "rpm:1200, mode:auto"), vec!["rpm=1200", "mode=auto"]);
+        assert_eq!(values_of(&set, "boot took 350 ms"), vec!["took=350"]);
+        assert!(set.parse("boot complete").is_empty());
+
+        let broken = ParserConfig {
+            kind: ParserKind::Regex,
+            pattern: r"took \d+".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(ParserSet::new(&[broken]).errors.len(), 1);
+    }
+}
@@ -0,0 +1,181 @@
+//! Live plot data, the numeric channels parsed from received lines collected into one series
+//! per channel
+
+use std::collections::{ HashSet, VecDeque };
+
+use chrono::{ DateTime, Local };
+
+use crate::models::parsers::ParserSet;
+use crate::models::scrollback::{ Chunk, Direction, LineSplitter };
+
+/// Seconds shown by the rolling time window unless changed
+pub const DEFAULT_PLOT_WINDOW_SECS: f64 = 10.0;
+
+/// Points kept per series, the oldest are dropped
+pub const MAX_PLOT_POINTS: usize = 100_000;
+
+pub struct Series {
+    pub name: String,
+    /// Seconds since the plot origin and the value, oldest first
+    pub points: VecDeque<[f64; 2]>,
+}
+
+pub struct PlotData {
+    pub series: Vec<Series>,
+    /// Names of the series hidden in the legend, left out of the value range
+    pub hidden: HashSet<String>,
+    /// Width of the rolling time window in seconds
+    pub window_secs: f64,
+    /// Time of x = 0, when the first value arrived
+    origin: Option<DateTime<Local>>,
+    /// Newest x, the right edge of the window
+    latest: f64,
+    /// Right edge of the window while paused, values keep being collected
+    paused_at: Option<f64>,
+    lines: LineSplitter,
+}
+
+impl Default for PlotData {
+    fn default() -> Self {
+        Self {
+            series: Vec::new(),
+            hidden: HashSet::new(),
+            window_secs: DEFAULT_PLOT_WINDOW_SECS,
+            origin: None,
+            latest: 0.0,
+            paused_at: None,
+            lines: LineSplitter::new(),
+        }
+    }
+}
+
+impl PlotData {
+    pub fn new() -> PlotData {
+        PlotData::default()
+    }
+
+    /// Adds the numbers `parsers` find in the received lines `chunk` completes
+    pub fn push(&mut self, chunk: &Chunk, parsers: &ParserSet) {
+        if chunk.direction != Direction::Rx {
+            return;
+        }
+        for line in self.lines.push(chunk) {
+            for sample in parsers.parse(&line.text) {
+                if let Some(value) = sample.value.as_number() {
+                    self.add(&sample.channel, line.at, value);
+                }
+            }
+        }
+    }
+
+    /// Adds a point at `at` to the series `name`, creating it if needed
+    pub fn add(&mut self, name: &str, at: DateTime<Local>, value: f64) {
+        let origin = *self.origin.get_or_insert(at);
+        let x = ((at - origin).num_microseconds().unwrap_or(i64::MAX) as f64) / 1e6;
+        self.latest = self.latest.max(x);
+
+        let index = match self.series.iter().position(|series| series.name == name) {
+            Some(index) => index,
+            None => {
+                self.series.push(Series { name: name.to_string(), points: VecDeque::new() });
+                self.series.len() - 1
+            }
+        };
+        let points = &mut self.series[index].points;
+        if points.len() == MAX_PLOT_POINTS {
+            points.pop_front();
+        }
+        points.push_back([x, value]);
+    }
+
+    /// Ends lines at carriage returns not followed by a line feed
+    pub fn set_cr_newline(&mut self, cr_newline: bool) {
+        self.lines.set_cr_newline(cr_newline);
+    }
+
+    pub fn clear(&mut self) {
+        let lines = std::mem::take(&mut self.lines);
+        *self = PlotData { window_secs: self.window_secs, lines, ..PlotData::default() };
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Freezes the window where it is, or lets it follow the newest values again
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused_at = if paused { Some(self.latest) } else { None };
+    }
+
+    /// The x range of the rolling window
+    pub fn window(&self) -> (f64, f64) {
+        let end = self.paused_at.unwrap_or(self.latest);
+        (end - self.window_secs, end)
+    }
+
+    /// The points of `series` inside the window
+    pub fn visible(&self, series: &Series) -> Vec<[f64; 2]> {
+        let (start, end) = self.window();
+        let first = series.points.partition_point(|point| point[0] < start);
+        let last = series.points.partition_point(|point| point[0] <= end);
+        series.points.range(first..last).copied().collect()
+    }
+
+    /// Smallest and largest value inside the window, over all series not hidden
+    pub fn value_range(&self) -> Option<(f64, f64)> {
+        self.series
+            .iter()
+            .filter(|series| !self.hidden.contains(&series.name))
+            .flat_map(|series| self.visible(series))
+            .map(|point| point[1])
+            .fold(None, |range, y| {
+                match range {
+                    None => Some((y, y)),
+                    Some((min, max)) => Some((f64::min(min, y), f64::max(max, y))),
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rolling_window_and_pause() {
+        let start = Local::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+        let mut plot = PlotData::new();
+        plot.window_secs = 5.0;
+
+        let parsers = ParserSet::default();
+        let chunk = |secs, direction, bytes: &[u8]| Chunk {
+            at: at(secs),
+            direction,
+            bytes: bytes.to_vec(),
+        };
+        plot.push(&chunk(0, Direction::Rx, b"t=1\n"), &parsers);
+        plot.push(&chunk(1, Direction::Tx, b"t=99\n"), &parsers);
+        for secs in 2..=8 {
+            plot.add("t", at(secs), secs as f64);
+        }
+        assert_eq!(plot.series.len(), 1);
+        assert_eq!(plot.window(), (3.0, 8.0));
+        assert_eq!(plot.visible(&plot.series[0]).len(), 6);
+        assert_eq!(plot.value_range(), Some((3.0, 8.0)));
+
+        plot.set_paused(true);
+        plot.add("t", at(20), 20.0);
+        plot.add("u", at(21), -1.0);
+        assert_eq!(plot.window(), (3.0, 8.0));
+        assert_eq!(plot.value_range(), Some((3.0, 8.0)));
+        plot.set_paused(false);
+        assert_eq!(plot.value_range(), Some((-1.0, 20.0)));
+        plot.hidden.insert("u".to_string());
+        assert_eq!(plot.value_range(), Some((20.0, 20.0)));
+
+        plot.clear();
+        assert!(plot.series.is_empty());
+        assert_eq!(plot.window_secs, 5.0);
+    }
+}
@@ -0,0 +1,195 @@
+//! Serial port enumeration and hot-plug detection
+
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+
+use serialport::{ SerialPortInfo, SerialPortType };
+
+/// How often the port list is refreshed while looking for plugged/unplugged devices
+pub const PORT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// One entry in the port list, flattened from `serialport::SerialPortInfo`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortEntry {
+    pub port_name: String,
+    pub kind: String,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    /// USB interface number, multi-channel adapters expose one port per interface
+    pub interface: Option<u8>,
+}
+
+impl From<&SerialPortInfo> for PortEntry {
+    fn from(info: &SerialPortInfo) -> Self {
+        let mut entry = PortEntry {
+            port_name: info.port_name.clone(),
+            ..Default::default()
+        };
+        match &info.port_type {
+            SerialPortType::UsbPort(usb_info) => {
+                entry.kind = "USB".to_string();
+                entry.vid = Some(usb_info.vid);
+                entry.pid = Some(usb_info.pid);
+                entry.serial_number = usb_info.serial_number.clone();
+                entry.manufacturer = usb_info.manufacturer.clone();
+                entry.product = usb_info.product.clone();
+                entry.interface = usb_info.interface;
+            }
+            SerialPortType::PciPort => {
+                entry.kind = "PCI".to_string();
+            }
+            SerialPortType::BluetoothPort => {
+                entry.kind = "Bluetooth".to_string();
+            }
+            SerialPortType::Unknown => {
+                entry.kind = "Unknown".to_string();
+            }
+        }
+        entry
+    }
+}
+
+impl PortEntry {
+    /// Vendor ID as four hex digits, or "-" when the port is not USB
+    pub fn vid_text(&self) -> String {
+        self.vid.map_or("-".to_string(), |vid| format!("{:04X}", vid))
+    }
+
+    /// Product ID as four hex digits, or "-" when the port is not USB
+    pub fn pid_text(&self) -> String {
+        self.pid.map_or("-".to_string(), |pid| format!("{:04X}", pid))
+    }
+
+    /// Multi-line description for the terminal, missing USB descriptors show as "-"
+    pub fn describe(&self) -> String {
+        let mut text = format!("{}\n", self.port_name);
+        text += &format!("Type          = {}\n", self.kind);
+        if self.kind == "USB" {
+            text += &format!("Manufacturer  = {}\n", self.manufacturer.as_deref().unwrap_or("-"));
+            text += &format!("Product       = {}\n", self.product.as_deref().unwrap_or("-"));
+            text += &format!("Serial Number = {}\n", self.serial_number.as_deref().unwrap_or("-"));
+            text += &format!("Vendor ID     = {}\n", self.vid_text());
+            text += &format!("Product ID    = {}\n", self.pid_text());
+        }
+        text
+    }
+}
+
+/// Lists the ports present right now, as opposed to the watched list
+pub fn list_ports() -> Result<Vec<PortEntry>, String> {
+    match serialport::available_ports() {
+        Ok(list) => Ok(list.iter().map(PortEntry::from).collect()),
+        Err(e) => Err(format!("Failed to enumerate serial ports: {}", e)),
+    }
+}
+
+/// Keeps a shared, periodically refreshed list of the ports present on the host
+#[derive(Debug, Clone, Default)]
+pub struct PortWatcher {
+    pub ports: Arc<Mutex<Vec<PortEntry>>>,
+    /// The last enumeration error, cleared once enumeration succeeds again
+    pub error: Arc<Mutex<Option<String>>>,
+}
+
+impl PortWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts polling `serialport::available_ports`, `on_change` is called whenever a
+    /// device is plugged or unplugged so that the UI can repaint right away.
+    pub fn spawn(&self, on_change: impl Fn() + Send + 'static) {
+        let ports = Arc::clone(&self.ports);
+        let error = Arc::clone(&self.error);
+        tokio::task::spawn(async move {
+            loop {
+                let found = match tokio::task::spawn_blocking(list_ports).await {
+                    Ok(found) => found,
+                    Err(e) => Err(format!("Serial port enumeration task failed: {}", e)),
+                };
+                match found {
+                    Ok(entries) => {
+                        *error.lock().unwrap() = None;
+                        let mut current = ports.lock().unwrap();
+                        if *current != entries {
+                            *current = entries;
+                            on_change();
+                        }
+                    }
+                    Err(e) => {
+                        let mut last = error.lock().unwrap();
+                        if last.as_ref() != Some(&e) {
+                            *last = Some(e);
+                            on_change();
+                        }
+                    }
+                }
+                tokio::time::sleep(PORT_POLL_INTERVAL).await;
+            }
+        });
+    }
+
+    /// A copy of the most recent port list
+    pub fn snapshot(&self) -> Vec<PortEntry> {
+        self.ports.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serialport::UsbPortInfo;
+
+    #[test]
+    fn usb_entry() {
+        let info = SerialPortInfo {
+            port_name: "/dev/ttyUSB1".to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: 0x0403,
+                pid: 0x6010,
+                serial_number: Some("210319B0C2F6".to_string()),
+                manufacturer: Some("Digilent".to_string()),
+                product: Some("Digilent USB Device".to_string()),
+                interface: Some(1),
+            }),
+        };
+        let entry = PortEntry::from(&info);
+        assert_eq!(entry.kind, "USB");
+        assert_eq!(entry.vid_text(), "0403");
+        assert_eq!(entry.pid_text(), "6010");
+        assert_eq!(entry.manufacturer.as_deref(), Some("Digilent"));
+    }
+
+    #[test]
+    fn ports_without_descriptors() {
+        let info = SerialPortInfo {
+            port_name: "/dev/ttyUSB0".to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: 0x1A86,
+                pid: 0x7523,
+                serial_number: None,
+                manufacturer: None,
+                product: None,
+                interface: None,
+            }),
+        };
+        let text = PortEntry::from(&info).describe();
+        assert!(text.contains("Serial Number = -"));
+        assert!(text.contains("Vendor ID     = 1A86"));
+
+        for (port_type, kind) in [
+            (SerialPortType::PciPort, "PCI"),
+            (SerialPortType::BluetoothPort, "Bluetooth"),
+            (SerialPortType::Unknown, "Unknown"),
+        ] {
+            let info = SerialPortInfo { port_name: "/dev/ttyS0".to_string(), port_type };
+            let entry = PortEntry::from(&info);
+            assert_eq!(entry.kind, kind);
+            assert_eq!(entry.vid_text(), "-");
+            assert!(!entry.describe().contains("Manufacturer"));
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Device profiles, mapping USB VID/PID and descriptor strings to a friendly name,
+//! line settings, preferred decoder and auto-connect policy
+
+use std::path::PathBuf;
+
+use regex::Regex;
+use serde::{ Deserialize, Serialize };
+
+use crate::models::ports::PortEntry;
+use crate::models::serial_config::LineOverrides;
+
+/// Profiles shipped with the application
+pub const DEFAULT_PROFILES: &str = include_str!("../../assets/profiles.toml");
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceProfile {
+    pub name: String,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub interface: Option<u8>,
+    /// Regex matched against the USB manufacturer string
+    pub manufacturer: Option<String>,
+    /// Regex matched against the USB product string
+    pub product: Option<String>,
+    /// Line settings to connect with, the ones left out are kept
+    pub line: Option<LineOverrides>,
+    pub decoder: Option<String>,
+    pub auto_connect: bool,
+}
+
+/// The on-disk layout of a profile file, a list of `[[profile]]` tables
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfileFile {
+    #[serde(default)]
+    profile: Vec<DeviceProfile>,
+}
+
+/// A profile with its descriptor patterns compiled
+#[derive(Debug, Clone)]
+struct Entry {
+    profile: DeviceProfile,
+    manufacturer: Option<Regex>,
+    product: Option<Regex>,
+}
+
+impl Entry {
+    fn new(profile: DeviceProfile) -> Result<Entry, String> {
+        let compile = |pattern: &Option<String>| -> Result<Option<Regex>, String> {
+            match pattern {
+                Some(pattern) =>
+                    Regex::new(pattern)
+                        .map(Some)
+                        .map_err(|e| format!("Profile '{}': {}", profile.name, e)),
+                None => Ok(None),
+            }
+        };
+        Ok(Entry {
+            manufacturer: compile(&profile.manufacturer)?,
+            product: compile(&profile.product)?,
+            profile,
+        })
+    }
+
+    fn matches(&self, port: &PortEntry) -> bool {
+        fn text_matches(pattern: &Option<Regex>, text: &Option<String>) -> bool {
+            match (pattern, text) {
+                (None, _) => true,
+                (Some(pattern), Some(text)) => pattern.is_match(text),
+                (Some(_), None) => false,
+            }
+        }
+        // a profile that names nothing to match on would claim every port
+        let has_criteria =
+            self.profile.vid.is_some() ||
+            self.profile.pid.is_some() ||
+            self.manufacturer.is_some() ||
+            self.product.is_some();
+
+        has_criteria &&
+            self.profile.vid.is_none_or(|vid| port.vid == Some(vid)) &&
+            self.profile.pid.is_none_or(|pid| port.pid == Some(pid)) &&
+            self.profile.interface.is_none_or(|interface| port.interface == Some(interface)) &&
+            text_matches(&self.manufacturer, &port.manufacturer) &&
+            text_matches(&self.product, &port.product)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfileDatabase {
+    entries: Vec<Entry>,
+}
+
+impl ProfileDatabase {
+    pub fn new() -> ProfileDatabase {
+        ProfileDatabase::default()
+    }
+
+    /// Location of the per-user profile file
+    pub fn user_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("arrakis").join("profiles.toml"))
+    }
+
+    /// Loads the shipped defaults and the user's profile file, if there is one. Problems are
+    /// returned as messages alongside whatever could be loaded.
+    pub fn load() -> (ProfileDatabase, Vec<String>) {
+        let mut database = ProfileDatabase::new();
+        let mut errors = Vec::new();
+
+        if let Some(path) = ProfileDatabase::user_path() {
+            if path.exists() {
+                match std::fs::read_to_string(&path) {
+                    Ok(text) => {
+                        if let Err(e) = database.add_toml(&text) {
+                            errors.push(format!("{}: {}", path.display(), e));
+                        }
+                    }
+                    Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+        }
+
+        let mut defaults = ProfileDatabase::new();
+        if let Err(e) = defaults.add_toml(DEFAULT_PROFILES) {
+            errors.push(format!("Default profiles: {}", e));
+        }
+        // user profiles replace shipped profiles of the same name
+        for entry in defaults.entries {
+            if !database.entries.iter().any(|user| user.profile.name == entry.profile.name) {
+                database.entries.push(entry);
+            }
+        }
+
+        (database, errors)
+    }
+
+    /// Appends the profiles of a TOML document, they are matched after existing profiles
+    pub fn add_toml(&mut self, text: &str) -> Result<(), String> {
+        let file: ProfileFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let entries = file.profile
+            .into_iter()
+            .map(Entry::new)
+            .collect::<Result<Vec<Entry>, String>>()?;
+        self.entries.extend(entries);
+        Ok(())
+    }
+
+    /// The first profile matching `port`
+    pub fn find(&self, port: &PortEntry) -> Option<&DeviceProfile> {
+        self.entries
+            .iter()
+            .find(|entry| entry.matches(port))
+            .map(|entry| &entry.profile)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn digilent_port(interface: u8) -> PortEntry {
+        PortEntry {
+            port_name: format!("/dev/ttyUSB{}", interface),
+            kind: "USB".to_string(),
+            vid: Some(0x0403),
+            pid: Some(0x6010),
+            manufacturer: Some("Digilent".to_string()),
+            product: Some("Digilent USB Device".to_string()),
+            interface: Some(interface),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_profiles() {
+        let mut database = ProfileDatabase::new();
+        database.add_toml(DEFAULT_PROFILES).unwrap();
+
+        let uart = database.find(&digilent_port(1)).unwrap();
+        assert_eq!(uart.name, "Arty A7 – UART");
+        assert!(uart.auto_connect);
+        assert_eq!(uart.line.as_ref().unwrap().baud_rate, Some(460800));
+
+        let jtag = database.find(&digilent_port(0)).unwrap();
+        assert_eq!(jtag.name, "Digilent FPGA Board");
+        assert!(!jtag.auto_connect);
+
+        let unknown = PortEntry { kind: "PCI".to_string(), ..Default::default() };
+        assert!(database.find(&unknown).is_none());
+    }
+
+    #[test]
+    fn user_profiles_come_first() {
+        let mut database = ProfileDatabase::new();
+        database
+            .add_toml(
+                r#"
+                [[profile]]
+                name = "Soft-core debug UART"
+                product = "USB Device$"
+                line = { baud_rate = 921600, parity = "Even" }
+                decoder = "hex"
+                "#
+            )
+            .unwrap();
+        database.add_toml(DEFAULT_PROFILES).unwrap();
+
+        let profile = database.find(&digilent_port(1)).unwrap();
+        assert_eq!(profile.name, "Soft-core debug UART");
+        assert_eq!(profile.decoder.as_deref(), Some("hex"));
+        let line = profile.line.as_ref().unwrap();
+        assert_eq!(line.summary(), "921600 baud, Even parity");
+    }
+
+    #[test]
+    fn bad_pattern() {
+        let mut database = ProfileDatabase::new();
+        let result = database.add_toml("[[profile]]\nname = \"x\"\nproduct = \"(\"\n");
+        assert!(result.is_err());
+        assert!(database.is_empty());
+    }
+}
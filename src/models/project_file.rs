@@ -0,0 +1,101 @@
+//! Project file, the Project tab settings, parser definitions, highlight rules and triggers
+//! saved as TOML in the project directory
+
+use std::path::Path;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::models::highlight::HighlightRule;
+use crate::models::parsers::ParserConfig;
+use crate::models::trigger::TriggerRule;
+
+/// Name of the project file in the project directory
+pub const PROJECT_FILE_NAME: &str = "arrakis-project.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectFile {
+    pub project_name: String,
+    pub module_name: String,
+    pub description: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub repo_url: String,
+    pub company: String,
+    #[serde(rename = "parser")]
+    pub parsers: Vec<ParserConfig>,
+    #[serde(rename = "highlight")]
+    pub highlights: Vec<HighlightRule>,
+    #[serde(rename = "trigger")]
+    pub triggers: Vec<TriggerRule>,
+}
+
+impl ProjectFile {
+    pub fn load(path: &Path) -> Result<ProjectFile, String> {
+        let text = std::fs
+            ::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::highlight::{ HighlightRule, Rgb };
+    use crate::models::parsers::{ ChannelConfig, ParserKind };
+    use crate::models::trigger::LogAction;
+
+    #[test]
+    fn round_trip() {
+        let file = ProjectFile {
+            project_name: "RealData_01Jan2026.csv".to_string(),
+            module_name: "adc".to_string(),
+            parsers: vec![ParserConfig {
+                name: "temperature".to_string(),
+                kind: ParserKind::Regex,
+                pattern: r"T=(?P<temp>-?\d+)".to_string(),
+                channels: vec![ChannelConfig {
+                    name: "temp".to_string(),
+                    unit: "°C".to_string(),
+                    scale: 0.1,
+                    offset: 0.0,
+                }],
+                ..Default::default()
+            }],
+            highlights: vec![HighlightRule {
+                name: "errors".to_string(),
+                pattern: "ERROR".to_string(),
+                foreground: Some(Rgb([0xff, 0x55, 0x55])),
+                ..Default::default()
+            }],
+            triggers: vec![TriggerRule {
+                name: "assert".to_string(),
+                pattern: "ASSERT".to_string(),
+                log: LogAction::Start,
+                count: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let text = toml::to_string_pretty(&file).unwrap();
+        assert!(text.contains("[[parser]]"));
+        assert!(text.contains("[[highlight]]"));
+        assert!(text.contains("foreground = \"#ff5555\""));
+        assert!(text.contains("[[trigger]]"));
+        assert!(text.contains("log = \"start\""));
+        assert_eq!(toml::from_str::<ProjectFile>(&text).unwrap(), file);
+
+        // a hand written parser only needs what differs from the defaults
+        let file: ProjectFile = toml
+            ::from_str("[[parser]]\nname = \"kv\"\nkind = \"key_value\"\n")
+            .unwrap();
+        assert_eq!(file.parsers[0].separator, "=");
+        assert!(file.parsers[0].enabled);
+    }
+}
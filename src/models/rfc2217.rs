@@ -0,0 +1,624 @@
+//! RFC 2217 client, a serial port on a remote server reached over Telnet whose line settings
+//! and control lines are changed with COM-PORT-OPTION subnegotiations
+
+use std::collections::{ HashMap, HashSet };
+use std::io::{ self, Read, Write };
+use std::net::TcpStream;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+
+use serialport::{ FlowControl, Parity, StopBits };
+
+use crate::models::serial_config::SerialConfig;
+use crate::models::transport::{ connect_tcp, Transport, WRITE_TIMEOUT };
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const BINARY: u8 = 0;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const COM_PORT_OPTION: u8 = 44;
+
+/// Options the client agrees to in either direction
+const SUPPORTED: [u8; 3] = [BINARY, SUPPRESS_GO_AHEAD, COM_PORT_OPTION];
+
+const SET_BAUDRATE: u8 = 1;
+const SET_DATASIZE: u8 = 2;
+const SET_PARITY: u8 = 3;
+const SET_STOPSIZE: u8 = 4;
+const SET_CONTROL: u8 = 5;
+
+const CONTROL_NO_FLOW: u8 = 1;
+const CONTROL_XON_XOFF: u8 = 2;
+const CONTROL_HARDWARE: u8 = 3;
+const CONTROL_DTR_ON: u8 = 8;
+const CONTROL_DTR_OFF: u8 = 9;
+const CONTROL_RTS_ON: u8 = 11;
+const CONTROL_RTS_OFF: u8 = 12;
+
+/// How long the server has to agree to COM-PORT-OPTION
+const NEGOTIATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long the server has to answer a setting with the value it set
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often a setting checks whether the reader got the answer
+const REPLY_POLL: Duration = Duration::from_millis(5);
+
+/// The server answers a COM-PORT-OPTION command with the command number raised by this
+const SERVER_OFFSET: u8 = 100;
+
+/// Where the Telnet parser is in the stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ParseState {
+    #[default]
+    Data,
+    Iac,
+    /// A DO, DONT, WILL or WONT waiting for its option
+    Negotiate(u8),
+    Sub,
+    SubIac,
+}
+
+/// Telnet commands found in the stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TelnetEvent {
+    Negotiate(u8, u8),
+    /// Contents of a subnegotiation, between `IAC SB` and `IAC SE`
+    Sub(Vec<u8>),
+}
+
+/// Splits a Telnet stream into data and commands, across reads
+#[derive(Debug, Default)]
+struct TelnetParser {
+    state: ParseState,
+    sub: Vec<u8>,
+}
+
+impl TelnetParser {
+    fn feed(&mut self, bytes: &[u8], data: &mut Vec<u8>, events: &mut Vec<TelnetEvent>) {
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (ParseState::Data, IAC) => ParseState::Iac,
+                (ParseState::Data, _) => {
+                    data.push(byte);
+                    ParseState::Data
+                }
+                (ParseState::Iac, IAC) => {
+                    data.push(IAC);
+                    ParseState::Data
+                }
+                (ParseState::Iac, DO | DONT | WILL | WONT) => ParseState::Negotiate(byte),
+                (ParseState::Iac, SB) => {
+                    self.sub.clear();
+                    ParseState::Sub
+                }
+                // NOP, go ahead and the other commands carry nothing for a serial port
+                (ParseState::Iac, _) => ParseState::Data,
+                (ParseState::Negotiate(verb), _) => {
+                    events.push(TelnetEvent::Negotiate(verb, byte));
+                    ParseState::Data
+                }
+                (ParseState::Sub, IAC) => ParseState::SubIac,
+                (ParseState::Sub, _) => {
+                    self.sub.push(byte);
+                    ParseState::Sub
+                }
+                (ParseState::SubIac, IAC) => {
+                    self.sub.push(IAC);
+                    ParseState::Sub
+                }
+                (ParseState::SubIac, _) => {
+                    events.push(TelnetEvent::Sub(std::mem::take(&mut self.sub)));
+                    ParseState::Data
+                }
+            };
+        }
+    }
+}
+
+/// Doubles the IAC bytes of `bytes`, so the server takes them as data
+fn escape(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        out.push(byte);
+        if byte == IAC {
+            out.push(IAC);
+        }
+    }
+}
+
+/// What a COM-PORT-OPTION command sets, for messages
+fn setting_name(command: u8) -> &'static str {
+    match command {
+        SET_BAUDRATE => "baud rate",
+        SET_DATASIZE => "data size",
+        SET_PARITY => "parity",
+        SET_STOPSIZE => "stop size",
+        _ => "control",
+    }
+}
+
+/// A value of a COM-PORT-OPTION command as a number, most significant byte first
+fn setting_value(value: &[u8]) -> u32 {
+    value.iter().fold(0, |number, &byte| (number << 8) | u32::from(byte))
+}
+
+/// A COM-PORT-OPTION subnegotiation setting `command` to `value`
+fn com_port_command(command: u8, value: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![IAC, SB, COM_PORT_OPTION, command];
+    escape(value, &mut bytes);
+    bytes.extend([IAC, SE]);
+    bytes
+}
+
+/// The COM-PORT-OPTION commands carrying `config` to the server, with their values
+fn line_commands(config: &SerialConfig) -> Vec<(u8, Vec<u8>)> {
+    let parity = match config.parity {
+        Parity::None => 1,
+        Parity::Odd => 2,
+        Parity::Even => 3,
+    };
+    let stop_bits = match config.stop_bits {
+        StopBits::One => 1,
+        StopBits::Two => 2,
+    };
+    let flow = match config.flow_control {
+        FlowControl::None => CONTROL_NO_FLOW,
+        FlowControl::Software => CONTROL_XON_XOFF,
+        FlowControl::Hardware => CONTROL_HARDWARE,
+    };
+    vec![
+        (SET_BAUDRATE, config.baud_rate.to_be_bytes().to_vec()),
+        (SET_DATASIZE, vec![u8::from(config.data_bits)]),
+        (SET_PARITY, vec![parity]),
+        (SET_STOPSIZE, vec![stop_bits]),
+        (SET_CONTROL, vec![flow])
+    ]
+}
+
+/// What the handles on one connection share, only the reader parses in practice
+#[derive(Debug, Default)]
+struct Negotiation {
+    parser: TelnetParser,
+    /// Data that arrived while negotiating, handed out by the next read
+    pending: Vec<u8>,
+    /// The last negotiation sent for each option and side, a request for the mode an option
+    /// is already in is not answered
+    sent: HashSet<(u8, u8)>,
+    /// The value the server answered each COM-PORT-OPTION command with, until it is checked
+    replies: HashMap<u8, Vec<u8>>,
+    /// Whether the server agreed to COM-PORT-OPTION, None until it answers
+    com_port: Option<bool>,
+}
+
+/// A remote serial port, the reader and the writer each hold one on the same socket
+pub struct Rfc2217 {
+    stream: TcpStream,
+    /// Every write goes through here, so replies from the reader never split a write
+    writer: Arc<Mutex<TcpStream>>,
+    negotiation: Arc<Mutex<Negotiation>>,
+}
+
+impl Rfc2217 {
+    /// Connects to the server at `address`, agrees on COM-PORT-OPTION and sends `config`
+    pub fn open(address: &str, config: &SerialConfig) -> Result<Rfc2217, String> {
+        let failed = |e: io::Error| format!("Failed to connect to rfc2217://{}: {}", address, e);
+        let stream = connect_tcp(address).map_err(failed)?;
+        let _ = stream.set_nodelay(true);
+        stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(failed)?;
+        let writer = stream.try_clone().map_err(failed)?;
+        let mut port = Rfc2217 {
+            stream,
+            writer: Arc::new(Mutex::new(writer)),
+            negotiation: Arc::new(Mutex::new(Negotiation::default())),
+        };
+        for (verb, option) in [
+            (WILL, COM_PORT_OPTION),
+            (WILL, BINARY),
+            (DO, BINARY),
+            (WILL, SUPPRESS_GO_AHEAD),
+            (DO, SUPPRESS_GO_AHEAD),
+        ] {
+            port.negotiate(verb, option).map_err(failed)?;
+        }
+
+        let deadline = Instant::now() + NEGOTIATION_TIMEOUT;
+        port.stream.set_read_timeout(Some(Duration::from_millis(100))).map_err(failed)?;
+        loop {
+            match port.negotiation.lock().unwrap().com_port {
+                Some(true) => break,
+                Some(false) => {
+                    return Err(format!("rfc2217://{} does not support RFC 2217", address));
+                }
+                None => {}
+            }
+            if Instant::now() > deadline {
+                return Err(format!("rfc2217://{} did not agree to RFC 2217", address));
+            }
+            match port.fill() {
+                Ok(0) => {
+                    return Err(format!("rfc2217://{} closed the connection", address));
+                }
+                Ok(_) => {}
+                Err(e) if is_quiet(&e) => {}
+                Err(e) => {
+                    return Err(failed(e));
+                }
+            }
+        }
+
+        port.command(&line_commands(config), true)?;
+        port.stream
+            .set_read_timeout(Some(Duration::from_millis(config.timeout_ms.max(1))))
+            .map_err(failed)?;
+        Ok(port)
+    }
+
+    /// Sends `IAC verb option` unless the option is already in that mode
+    fn negotiate(&self, verb: u8, option: u8) -> io::Result<()> {
+        let opposite = match verb {
+            WILL => WONT,
+            WONT => WILL,
+            DO => DONT,
+            _ => DO,
+        };
+        {
+            let mut negotiation = self.negotiation.lock().unwrap();
+            if !negotiation.sent.insert((verb, option)) {
+                return Ok(());
+            }
+            negotiation.sent.remove(&(opposite, option));
+        }
+        self.writer.lock().unwrap().write_all(&[IAC, verb, option])
+    }
+
+    /// Answers what the server asks for as RFC 854 has it, agreeing to the supported options
+    /// only and acknowledging an option the server turns off, and keeps the values the server
+    /// answers the settings with
+    fn handle(&self, event: TelnetEvent) -> io::Result<()> {
+        let (verb, option) = match event {
+            TelnetEvent::Negotiate(verb, option) => (verb, option),
+            TelnetEvent::Sub(sub) => {
+                // line and modem state notifications are not used
+                if let [COM_PORT_OPTION, reply, value @ ..] = &sub[..] {
+                    if let Some(command) = reply.checked_sub(SERVER_OFFSET) {
+                        self.negotiation.lock().unwrap().replies.insert(command, value.to_vec());
+                    }
+                }
+                return Ok(());
+            }
+        };
+        let supported = SUPPORTED.contains(&option);
+        if option == COM_PORT_OPTION && matches!(verb, DO | DONT) {
+            self.negotiation.lock().unwrap().com_port = Some(verb == DO);
+        }
+        let enabled = |verb| self.negotiation.lock().unwrap().sent.contains(&(verb, option));
+        match verb {
+            DO if supported => self.negotiate(WILL, option),
+            DO => self.negotiate(WONT, option),
+            WILL if supported => self.negotiate(DO, option),
+            WILL => self.negotiate(DONT, option),
+            DONT if enabled(WILL) => self.negotiate(WONT, option),
+            WONT if enabled(DO) => self.negotiate(DONT, option),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sends the COM-PORT-OPTION `commands` and checks that the server answers each with the
+    /// value sent. The answers are read here with `fill` until a reader does that.
+    fn command(&mut self, commands: &[(u8, Vec<u8>)], fill: bool) -> Result<(), String> {
+        let mut bytes = Vec::new();
+        {
+            let mut negotiation = self.negotiation.lock().unwrap();
+            for (command, value) in commands {
+                negotiation.replies.remove(command);
+                bytes.extend(com_port_command(*command, value));
+            }
+        }
+        self.send(&bytes)?;
+
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        for (command, value) in commands {
+            let name = setting_name(*command);
+            let reply = loop {
+                let reply = self.negotiation.lock().unwrap().replies.remove(command);
+                if let Some(reply) = reply {
+                    break reply;
+                }
+                if Instant::now() > deadline {
+                    return Err(format!("The RFC 2217 server did not confirm the {}", name));
+                }
+                if !fill {
+                    std::thread::sleep(REPLY_POLL);
+                    continue;
+                }
+                match self.fill() {
+                    Ok(0) => {
+                        return Err("The RFC 2217 server closed the connection".to_string());
+                    }
+                    Ok(_) => {}
+                    Err(e) if is_quiet(&e) => {}
+                    Err(e) => {
+                        return Err(format!("Failed to read from the RFC 2217 server: {}", e));
+                    }
+                }
+            };
+            if reply != *value {
+                return Err(
+                    format!(
+                        "The RFC 2217 server set the {} to {} instead of {}",
+                        name,
+                        setting_value(&reply),
+                        setting_value(value)
+                    )
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads once from the socket, keeping the data and handling the commands, returns the
+    /// number of bytes read
+    fn fill(&mut self) -> io::Result<usize> {
+        let mut raw = [0u8; 4096];
+        let count = self.stream.read(&mut raw)?;
+        let mut events = Vec::new();
+        {
+            let mut negotiation = self.negotiation.lock().unwrap();
+            let Negotiation { parser, pending, .. } = &mut *negotiation;
+            parser.feed(&raw[..count], pending, &mut events);
+        }
+        for event in events {
+            self.handle(event)?;
+        }
+        Ok(count)
+    }
+
+    fn send(&self, bytes: &[u8]) -> Result<(), String> {
+        self.writer
+            .lock()
+            .unwrap()
+            .write_all(bytes)
+            .map_err(|e| format!("Failed to write to the RFC 2217 server: {}", e))
+    }
+}
+
+fn is_quiet(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+}
+
+impl Read for Rfc2217 {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            {
+                let mut negotiation = self.negotiation.lock().unwrap();
+                if !negotiation.pending.is_empty() {
+                    let count = buffer.len().min(negotiation.pending.len());
+                    buffer[..count].copy_from_slice(&negotiation.pending[..count]);
+                    negotiation.pending.drain(..count);
+                    return Ok(count);
+                }
+            }
+            // a read of nothing but commands goes on waiting for data
+            if self.fill()? == 0 {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+impl Write for Rfc2217 {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let mut bytes = Vec::with_capacity(buffer.len());
+        escape(buffer, &mut bytes);
+        self.writer.lock().unwrap().write_all(&bytes)?;
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+impl Transport for Rfc2217 {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(
+            Box::new(Rfc2217 {
+                stream: self.stream.try_clone()?,
+                writer: Arc::clone(&self.writer),
+                negotiation: Arc::clone(&self.negotiation),
+            })
+        )
+    }
+
+    /// Checked against the answers of the server, the reader of the connection reads them
+    fn set_config(&mut self, config: &SerialConfig) -> Result<(), String> {
+        self.command(&line_commands(config), false)
+    }
+
+    fn set_dtr(&mut self, on: bool) -> Result<(), String> {
+        let control = if on { CONTROL_DTR_ON } else { CONTROL_DTR_OFF };
+        self.command(&[(SET_CONTROL, vec![control])], false)
+    }
+
+    fn set_rts(&mut self, on: bool) -> Result<(), String> {
+        let control = if on { CONTROL_RTS_ON } else { CONTROL_RTS_OFF };
+        self.command(&[(SET_CONTROL, vec![control])], false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+
+    use serialport::DataBits;
+
+    use super::*;
+
+    /// A minimal RFC 2217 server, it agrees to COM-PORT-OPTION, offers ECHO and takes it
+    /// back, turns off BINARY, echoes the settings it gets like a real server, with at most
+    /// 115200 baud, and sends data with an IAC in it
+    fn server(listener: TcpListener) -> std::thread::JoinHandle<Vec<TelnetEvent>> {
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(&[IAC, DO, COM_PORT_OPTION, IAC, WILL, 1]).unwrap();
+            stream.write_all(&[IAC, DONT, BINARY, IAC, WONT, 1]).unwrap();
+            stream.write_all(&[b'o', b'k', IAC, IAC, b'\n']).unwrap();
+            let mut parser = TelnetParser::default();
+            let mut data = Vec::new();
+            let mut events = Vec::new();
+            let mut buffer = [0u8; 256];
+            // the client ends with the data it sends
+            while !data.ends_with(b"bye") {
+                let count = stream.read(&mut buffer).unwrap();
+                let start = events.len();
+                parser.feed(&buffer[..count], &mut data, &mut events);
+                for event in &events[start..] {
+                    if let TelnetEvent::Sub(sub) = event {
+                        let mut echo = sub.clone();
+                        echo[1] += SERVER_OFFSET;
+                        if echo[1] == SERVER_OFFSET + SET_BAUDRATE {
+                            let baud = setting_value(&echo[2..]).min(115200);
+                            echo.splice(2.., baud.to_be_bytes());
+                        }
+                        stream.write_all(&[IAC, SB]).unwrap();
+                        stream.write_all(&echo).unwrap();
+                        stream.write_all(&[IAC, SE]).unwrap();
+                    }
+                }
+            }
+            assert_eq!(data, [b'a', IAC, b'b', b'y', b'e']);
+            events
+        })
+    }
+
+    #[test]
+    fn parse_telnet() {
+        let mut parser = TelnetParser::default();
+        let mut data = Vec::new();
+        let mut events = Vec::new();
+        let first = [b'a', IAC, IAC, IAC, DO, 44, b'b', IAC, SB, 44, 1, IAC];
+        parser.feed(&first, &mut data, &mut events);
+        // a subnegotiation split across reads
+        parser.feed(&[IAC, IAC, SE, b'c'], &mut data, &mut events);
+        assert_eq!(data, [b'a', IAC, b'b', b'c']);
+        assert_eq!(
+            events,
+            vec![TelnetEvent::Negotiate(DO, 44), TelnetEvent::Sub(vec![44, 1, IAC])]
+        );
+    }
+
+    #[test]
+    fn remote_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = server(listener);
+
+        let config = SerialConfig {
+            baud_rate: 115200,
+            data_bits: DataBits::Seven,
+            parity: Parity::Even,
+            stop_bits: StopBits::Two,
+            timeout_ms: 1000,
+            ..SerialConfig::new()
+        };
+        let mut port = Rfc2217::open(&address, &config).unwrap();
+        let mut reader = Transport::try_clone(&port).unwrap();
+        let mut buffer = [0u8; 16];
+        let mut data = Vec::new();
+        while data.len() < 4 {
+            let count = reader.read(&mut buffer).unwrap();
+            data.extend_from_slice(&buffer[..count]);
+        }
+        assert_eq!(data, [b'o', b'k', IAC, b'\n']);
+
+        // the answers to the settings come through the reader, as in a connection
+        let reading = std::thread::spawn(move || {
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) => {
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) if is_quiet(&e) => {}
+                    Err(_) => {
+                        break;
+                    }
+                }
+            }
+        });
+        port.set_dtr(false).unwrap();
+        port.set_rts(true).unwrap();
+        assert_eq!(
+            port.set_config(&SerialConfig { baud_rate: 230400, ..config.clone() }),
+            Err("The RFC 2217 server set the baud rate to 115200 instead of 230400".to_string())
+        );
+        port.write_all(&[b'a', IAC, b'b', b'y', b'e']).unwrap();
+        let events = server.join().unwrap();
+        reading.join().unwrap();
+
+        // COM-PORT-OPTION was offered once even though the server asked for it, ECHO was
+        // refused, turning off BINARY was acknowledged and ECHO being taken back was not
+        let negotiations = events
+            .iter()
+            .filter(|event| matches!(event, TelnetEvent::Negotiate(_, _)))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            negotiations,
+            vec![
+                TelnetEvent::Negotiate(WILL, COM_PORT_OPTION),
+                TelnetEvent::Negotiate(WILL, BINARY),
+                TelnetEvent::Negotiate(DO, BINARY),
+                TelnetEvent::Negotiate(WILL, SUPPRESS_GO_AHEAD),
+                TelnetEvent::Negotiate(DO, SUPPRESS_GO_AHEAD),
+                TelnetEvent::Negotiate(DONT, 1),
+                TelnetEvent::Negotiate(WONT, BINARY)
+            ]
+        );
+        let subs = events
+            .into_iter()
+            .filter_map(|event| match event {
+                TelnetEvent::Sub(sub) => Some(sub),
+                TelnetEvent::Negotiate(_, _) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            subs,
+            vec![
+                vec![COM_PORT_OPTION, SET_BAUDRATE, 0, 1, 0xc2, 0x00],
+                vec![COM_PORT_OPTION, SET_DATASIZE, 7],
+                vec![COM_PORT_OPTION, SET_PARITY, 3],
+                vec![COM_PORT_OPTION, SET_STOPSIZE, 2],
+                vec![COM_PORT_OPTION, SET_CONTROL, CONTROL_NO_FLOW],
+                vec![COM_PORT_OPTION, SET_CONTROL, CONTROL_DTR_OFF],
+                vec![COM_PORT_OPTION, SET_CONTROL, CONTROL_RTS_ON],
+                vec![COM_PORT_OPTION, SET_BAUDRATE, 0, 3, 0x84, 0x00],
+                vec![COM_PORT_OPTION, SET_DATASIZE, 7],
+                vec![COM_PORT_OPTION, SET_PARITY, 3],
+                vec![COM_PORT_OPTION, SET_STOPSIZE, 2],
+                vec![COM_PORT_OPTION, SET_CONTROL, CONTROL_NO_FLOW]
+            ]
+        );
+    }
+
+    #[test]
+    fn refused_by_plain_telnet() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(&[IAC, DONT, COM_PORT_OPTION]).unwrap();
+            let mut buffer = [0u8; 64];
+            let _ = stream.read(&mut buffer);
+        });
+        let err = Rfc2217::open(&address, &SerialConfig::new()).err().unwrap();
+        assert!(err.ends_with("does not support RFC 2217"));
+        server.join().unwrap();
+    }
+}
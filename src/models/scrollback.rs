@@ -0,0 +1,1159 @@
+//! Terminal scrollback, every byte received or sent kept as-is with its arrival time,
+//! the terminal views (text, hex dump and mixed) are rendered from it. The scrollback is
+//! bounded, the oldest lines are dropped or optionally spilled to a file on disk.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+use std::path::{ Path, PathBuf };
+
+use chrono::{ DateTime, Local };
+
+use crate::models::ansi::{ strip, LineEditor };
+
+/// Where the bytes of a chunk came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from the port
+    Rx,
+    /// Sent to the port
+    Tx,
+    /// Messages from the application itself
+    Local,
+}
+
+/// Bytes as they were handed over by the reader, or written to the port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub at: DateTime<Local>,
+    pub direction: Direction,
+    pub bytes: Vec<u8>,
+}
+
+impl Chunk {
+    pub fn new(direction: Direction, bytes: Vec<u8>) -> Chunk {
+        Chunk { at: Local::now(), direction, bytes }
+    }
+}
+
+/// How the terminal renders the bytes of the port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViewMode {
+    /// UTF-8 text, invalid sequences and control characters escaped
+    #[default]
+    Text,
+    /// Offset, 16 bytes per row and an ASCII gutter
+    Hex,
+    /// Printable ASCII as text, every other byte as `<0x1B>`
+    Mixed,
+}
+
+pub const VIEW_MODES: [ViewMode; 3] = [ViewMode::Text, ViewMode::Hex, ViewMode::Mixed];
+
+/// Bytes per row of the hex dump
+pub const HEX_ROW_LEN: usize = 16;
+
+impl ViewMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ViewMode::Text => "Text",
+            ViewMode::Hex => "Hex",
+            ViewMode::Mixed => "Mixed",
+        }
+    }
+
+    /// Parses a decoder name as used in device profiles, i.e. "hex"
+    pub fn from_name(name: &str) -> Option<ViewMode> {
+        VIEW_MODES.into_iter().find(|mode| mode.label().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Streaming UTF-8 decoder for the text view. A sequence split across chunks is completed
+/// by the next chunk, invalid bytes and control characters are shown as `\xNN` escapes.
+#[derive(Debug, Default)]
+pub struct TextDecoder {
+    /// Start of a multi-byte sequence waiting for the rest of its bytes
+    pending: Vec<u8>,
+    direction: Option<Direction>,
+    /// Pass escape, carriage return, backspace and bell through for an `AnsiParser`
+    pub controls: bool,
+    /// A carriage return ends the line, for devices that send no line feeds. A line feed right
+    /// after it belongs to the same line ending.
+    pub cr_newline: bool,
+    /// The last character was a carriage return turned into a line feed
+    after_cr: bool,
+}
+
+impl TextDecoder {
+    pub fn new() -> TextDecoder {
+        TextDecoder::default()
+    }
+
+    pub fn decode(&mut self, chunk: &Chunk) -> String {
+        let mut text = String::new();
+        if self.direction != Some(chunk.direction) {
+            // an incomplete sequence can not be continued by the other side
+            escape_bytes(&mut text, &std::mem::take(&mut self.pending));
+            self.direction = Some(chunk.direction);
+            self.after_cr = false;
+        }
+
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(&chunk.bytes);
+
+        let mut rest = &data[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    self.push_printable(&mut text, valid);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    let valid = std::str::from_utf8(valid).unwrap_or_default();
+                    self.push_printable(&mut text, valid);
+                    match e.error_len() {
+                        Some(len) => {
+                            escape_bytes(&mut text, &after[..len]);
+                            self.after_cr = false;
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.pending = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    fn push_printable(&mut self, text: &mut String, valid: &str) {
+        for c in valid.chars() {
+            let after_cr = std::mem::take(&mut self.after_cr);
+            match c {
+                '\r' if self.cr_newline => {
+                    text.push('\n');
+                    self.after_cr = true;
+                }
+                '\n' if after_cr => {}
+                '\x1b' | '\r' | '\x08' | '\x07' if self.controls => text.push(c),
+                // line endings are normalised to '\n'
+                '\r' => {}
+                '\n' | '\t' => text.push(c),
+                c if c.is_control() => {
+                    let mut utf8 = [0u8; 4];
+                    escape_bytes(text, c.encode_utf8(&mut utf8).as_bytes());
+                }
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+/// A line received or sent, with the time its first byte arrived
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextLine {
+    pub at: DateTime<Local>,
+    pub direction: Direction,
+    pub text: String,
+}
+
+/// Splits chunks into lines of text. A line may span chunks and ends at a line feed, after
+/// `MAX_LINE_LEN` characters or when the other side starts talking, messages of the
+/// application are skipped.
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    decoder: TextDecoder,
+    /// The line as the terminal shows it, escape sequences and carriage returns edit it
+    editor: LineEditor,
+    /// When the line being received started and who sends it
+    started: Option<(DateTime<Local>, Direction)>,
+}
+
+impl LineSplitter {
+    pub fn new() -> LineSplitter {
+        let mut splitter = LineSplitter::default();
+        splitter.decoder.controls = true;
+        splitter
+    }
+
+    /// Ends lines at carriage returns not followed by a line feed
+    pub fn set_cr_newline(&mut self, cr_newline: bool) {
+        self.decoder.cr_newline = cr_newline;
+    }
+
+    /// The lines `chunk` completes
+    pub fn push(&mut self, chunk: &Chunk) -> Vec<TextLine> {
+        let mut lines = Vec::new();
+        if chunk.direction == Direction::Local {
+            return lines;
+        }
+        if self.started.is_some_and(|(_, direction)| direction != chunk.direction) {
+            lines.extend(self.finish());
+        }
+        for c in self.decoder.decode(chunk).chars() {
+            self.started.get_or_insert((chunk.at, chunk.direction));
+            if c != '\n' {
+                self.editor.feed(c);
+            }
+            if c == '\n' || self.editor.width() >= MAX_LINE_LEN {
+                lines.extend(self.finish());
+            }
+        }
+        lines
+    }
+
+    /// The line still being received
+    pub fn finish(&mut self) -> Option<TextLine> {
+        let (at, direction) = self.started.take()?;
+        let text = self.editor.text();
+        self.editor.new_line();
+        Some(TextLine { at, direction, text })
+    }
+}
+
+/// Timestamp shown in front of every received or sent line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampMode {
+    #[default]
+    Off,
+    /// Wall clock time of day
+    Absolute,
+    /// Seconds since the port was opened
+    SinceConnect,
+    /// Seconds since the previous line started
+    Delta,
+}
+
+pub const TIMESTAMP_MODES: [TimestampMode; 4] = [
+    TimestampMode::Off,
+    TimestampMode::Absolute,
+    TimestampMode::SinceConnect,
+    TimestampMode::Delta,
+];
+
+impl TimestampMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimestampMode::Off => "Off",
+            TimestampMode::Absolute => "Clock",
+            TimestampMode::SinceConnect => "Since Connect",
+            TimestampMode::Delta => "Delta",
+        }
+    }
+}
+
+/// Seconds from `from` to `to` at microsecond resolution
+fn seconds_between(from: DateTime<Local>, to: DateTime<Local>) -> f64 {
+    ((to - from).num_microseconds().unwrap_or(i64::MAX) as f64) / 1e6
+}
+
+/// One row of the hex dump, `hexdump -C` style with the direction in front
+fn hex_row(prefix: &str, direction: Direction, offset: usize, bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for (i, byte) in bytes.iter().enumerate() {
+        if i == HEX_ROW_LEN / 2 {
+            hex.push(' ');
+        }
+        hex.push_str(&format!("{:02X} ", byte));
+    }
+    let ascii: String = bytes
+        .iter()
+        .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+        .collect();
+    let marker = if direction == Direction::Tx { "TX" } else { "RX" };
+    format!("{}{} {:08X}  {:<49} |{}|\n", prefix, marker, offset, hex, ascii)
+}
+
+/// Renders chunks in one of the view modes, appending to (and for the hex dump rewriting
+/// the last row of) the view
+#[derive(Debug, Default)]
+pub struct ViewDecoder {
+    pub mode: ViewMode,
+    pub timestamps: TimestampMode,
+    /// When the port was opened, for `TimestampMode::SinceConnect`
+    pub connected_at: Option<DateTime<Local>>,
+    text: TextDecoder,
+    /// Follow ANSI escape sequences in text mode, rather than showing them escaped
+    pub ansi: bool,
+    /// The line being received, as edited by escape sequences
+    editor: LineEditor,
+    /// Where the text of `editor` starts in the view, once it has some
+    editor_start: Option<usize>,
+    editor_direction: Option<Direction>,
+    /// Start of the previous received or sent line
+    last_line_at: Option<DateTime<Local>>,
+    /// Something has been written on the current line of the view
+    mid_line: bool,
+    /// Characters written on the current line of the view, up to `MAX_LINE_LEN`
+    line_len: usize,
+    /// Hex row still being filled, its text starts at `row_start` in the view
+    row: Vec<u8>,
+    row_start: usize,
+    row_direction: Option<Direction>,
+    row_prefix: String,
+    /// Offset of the next hex row, counted separately for received and sent bytes
+    rx_offset: usize,
+    tx_offset: usize,
+}
+
+impl ViewDecoder {
+    pub fn new(mode: ViewMode, timestamps: TimestampMode) -> ViewDecoder {
+        ViewDecoder { mode, timestamps, ..Default::default() }
+    }
+
+    pub fn decode(&mut self, chunk: &Chunk, view: &mut String) {
+        if chunk.direction == Direction::Local {
+            // messages of the application are text in every mode, without timestamps
+            self.finish_row();
+            self.leave_editor();
+            self.text.controls = false;
+            let text = self.text.decode(chunk);
+            self.push_lines(&text, None, view);
+            return;
+        }
+        match self.mode {
+            ViewMode::Text if self.ansi => {
+                self.text.controls = true;
+                let text = self.text.decode(chunk);
+                self.push_edited(&text, chunk, view);
+            }
+            ViewMode::Text => {
+                self.text.controls = false;
+                let text = self.text.decode(chunk);
+                self.push_lines(&text, Some(chunk.at), view);
+            }
+            ViewMode::Hex => self.decode_hex(chunk, view),
+            ViewMode::Mixed => {
+                self.leave_editor();
+                let mut text = String::new();
+                for &byte in &chunk.bytes {
+                    match byte {
+                        b'\n' => text.push_str("<0x0A>\n"),
+                        byte if byte.is_ascii_graphic() || byte == b' ' => text.push(byte as char),
+                        byte => text.push_str(&format!("<0x{:02X}>", byte)),
+                    }
+                }
+                self.push_lines(&text, Some(chunk.at), view);
+            }
+        }
+    }
+
+    /// Appends `text`, putting the timestamp for `at` in front of every line it starts
+    fn push_lines(&mut self, text: &str, at: Option<DateTime<Local>>, view: &mut String) {
+        for c in text.chars() {
+            if !self.mid_line && c != '\n' {
+                if let Some(at) = at {
+                    view.push_str(&self.line_prefix(at));
+                }
+                self.mid_line = true;
+            }
+            view.push(c);
+            if c == '\n' {
+                self.mid_line = false;
+                self.line_len = 0;
+                continue;
+            }
+            self.line_len += 1;
+            if self.line_len == MAX_LINE_LEN {
+                view.push('\n');
+                self.mid_line = false;
+                self.line_len = 0;
+            }
+        }
+    }
+
+    /// Appends `text` through the line editor, the line being received is written again when
+    /// an escape sequence or carriage return changes it
+    fn push_edited(&mut self, text: &str, chunk: &Chunk, view: &mut String) {
+        if self.editor_direction != Some(chunk.direction) {
+            self.leave_editor();
+            self.editor_direction = Some(chunk.direction);
+        }
+        for c in text.chars() {
+            if c == '\n' {
+                self.end_edited_line(view);
+                continue;
+            }
+            if !self.mid_line {
+                view.push_str(&self.line_prefix(chunk.at));
+                self.mid_line = true;
+            }
+            if self.editor_start.is_none() {
+                self.editor_start = Some(view.len());
+            }
+            self.editor.feed(c);
+            if self.editor.width() >= MAX_LINE_LEN {
+                self.end_edited_line(view);
+            }
+        }
+        if let Some(start) = self.editor_start {
+            self.editor.write(view, start);
+        }
+    }
+
+    /// Writes out the line being edited and starts the next one
+    fn end_edited_line(&mut self, view: &mut String) {
+        if let Some(start) = self.editor_start.take() {
+            self.editor.write(view, start);
+        }
+        self.editor.new_line();
+        view.push('\n');
+        self.mid_line = false;
+    }
+
+    /// Lets something else write to the view, the line editor can no longer reach back over
+    /// what it wrote
+    fn leave_editor(&mut self) {
+        self.editor.new_line();
+        self.editor_start = None;
+        self.editor_direction = None;
+    }
+
+    /// The timestamp of a line starting at `at`
+    fn line_prefix(&mut self, at: DateTime<Local>) -> String {
+        let prefix = match self.timestamps {
+            TimestampMode::Off => String::new(),
+            TimestampMode::Absolute => format!("[{}] ", at.format("%H:%M:%S%.6f")),
+            TimestampMode::SinceConnect => {
+                let since = self.connected_at.map(|connected_at| seconds_between(connected_at, at));
+                match since {
+                    Some(since) => format!("[+{:>11.6}] ", since),
+                    None => format!("[+{:>11}] ", "-"),
+                }
+            }
+            TimestampMode::Delta => {
+                let delta = self.last_line_at.map_or(0.0, |last| seconds_between(last, at));
+                format!("[Δ{:>11.6}] ", delta)
+            }
+        };
+        self.last_line_at = Some(at);
+        prefix
+    }
+
+    fn decode_hex(&mut self, chunk: &Chunk, view: &mut String) {
+        if self.row_direction != Some(chunk.direction) {
+            self.finish_row();
+            self.row_direction = Some(chunk.direction);
+        }
+        // take back the partial row, unless the view changed underneath it
+        if !self.row.is_empty() && view.is_char_boundary(self.row_start) {
+            view.truncate(self.row_start);
+        }
+        let mut offset = match chunk.direction {
+            Direction::Tx => self.tx_offset,
+            _ => self.rx_offset,
+        };
+        for &byte in &chunk.bytes {
+            if self.row.is_empty() {
+                self.row_prefix = self.line_prefix(chunk.at);
+            }
+            self.row.push(byte);
+            if self.row.len() == HEX_ROW_LEN {
+                view.push_str(&hex_row(&self.row_prefix, chunk.direction, offset, &self.row));
+                offset += HEX_ROW_LEN;
+                self.row.clear();
+            }
+        }
+        match chunk.direction {
+            Direction::Tx => {
+                self.tx_offset = offset;
+            }
+            _ => {
+                self.rx_offset = offset;
+            }
+        }
+        self.row_start = view.len();
+        if !self.row.is_empty() {
+            view.push_str(&hex_row(&self.row_prefix, chunk.direction, offset, &self.row));
+        }
+        self.mid_line = false;
+        self.line_len = 0;
+    }
+
+    /// Where the hex row still being filled starts in the view, it is rewritten as bytes arrive
+    pub fn pending_row_start(&self) -> Option<usize> {
+        if self.row.is_empty() { None } else { Some(self.row_start) }
+    }
+
+    /// Tells the decoder that `count` bytes were taken from the front of the view
+    pub fn shift(&mut self, count: usize) {
+        self.row_start = self.row_start.saturating_sub(count);
+        self.editor_start = self.editor_start.map(|start| start.saturating_sub(count));
+    }
+
+    /// Leaves a partial hex row as it is, the next bytes start a new row
+    fn finish_row(&mut self) {
+        match self.row_direction {
+            Some(Direction::Tx) => {
+                self.tx_offset += self.row.len();
+            }
+            Some(_) => {
+                self.rx_offset += self.row.len();
+            }
+            None => {}
+        }
+        self.row.clear();
+        self.row_direction = None;
+    }
+}
+
+fn escape_bytes(text: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        text.push_str(&format!("\\x{:02X}", byte));
+    }
+}
+
+/// Characters a line of the view holds before it is broken, so data without line feeds still
+/// scrolls out and is bounded like any other
+pub const MAX_LINE_LEN: usize = 4096;
+
+/// Lines kept in the scrollback unless configured otherwise
+pub const DEFAULT_MAX_LINES: usize = 100_000;
+
+/// Bytes held back while paused before the oldest of them go into the view anyway
+pub const MAX_HELD_BYTES: usize = 16 << 20;
+
+/// Chunks arrived while the view is paused
+#[derive(Debug, Default)]
+struct Held {
+    chunks: VecDeque<Chunk>,
+    bytes: usize,
+    /// Line feeds in `chunks`
+    lines: usize,
+}
+
+impl Held {
+    fn push(&mut self, chunk: Chunk) {
+        self.bytes += chunk.bytes.len();
+        self.lines += chunk.bytes
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count();
+        self.chunks.push_back(chunk);
+    }
+
+    fn pop(&mut self) -> Option<Chunk> {
+        let chunk = self.chunks.pop_front()?;
+        self.bytes -= chunk.bytes.len();
+        self.lines -= chunk.bytes
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count();
+        Some(chunk)
+    }
+}
+
+/// File the lines dropped from the front of the scrollback are appended to
+#[derive(Debug)]
+pub struct Spill {
+    pub path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl Spill {
+    /// Creates a new file in `dir`, named after the current time
+    pub fn create(dir: &Path) -> Result<Spill, String> {
+        std::fs
+            ::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let name = format!("scrollback-{}.txt", Local::now().format("%Y%m%d-%H%M%S%.3f"));
+        let path = dir.join(name);
+        let file = File::create(&path).map_err(|e|
+            format!("Failed to create {}: {}", path.display(), e)
+        )?;
+        Ok(Spill { path, file: BufWriter::new(file) })
+    }
+
+    /// Where spill files go unless told otherwise
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("arrakis"))
+    }
+}
+
+/// Everything shown in the terminal, in order
+#[derive(Debug)]
+pub struct Scrollback {
+    /// Chunks still shown in the view, the view is re-rendered from them
+    pub chunks: VecDeque<Chunk>,
+    /// Number of the view line each chunk in `chunks` starts on
+    chunk_lines: VecDeque<usize>,
+    /// Complete lines of the view, `chunks` rendered in the current view mode
+    lines: VecDeque<String>,
+    /// The line being written, followed by the hex row still being filled
+    tail: String,
+    /// Number of the first line in `lines`, counting the lines dropped before it
+    first_line: usize,
+    /// Complete lines kept before the oldest are dropped
+    max_lines: usize,
+    /// Counts the times the view was rendered from scratch, its lines numbered anew
+    generation: u64,
+    spill: Option<Spill>,
+    /// Chunks arrived while the view is paused, shown when it resumes
+    held: Option<Held>,
+    /// Spilling failed and was stopped, for the terminal
+    error: Option<String>,
+    /// When the port was opened, each time it was
+    connects: Vec<DateTime<Local>>,
+    decoder: ViewDecoder,
+}
+
+impl Default for Scrollback {
+    fn default() -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            chunk_lines: VecDeque::new(),
+            lines: VecDeque::new(),
+            tail: String::new(),
+            first_line: 0,
+            max_lines: DEFAULT_MAX_LINES,
+            generation: 0,
+            spill: None,
+            held: None,
+            error: None,
+            connects: Vec::new(),
+            decoder: ViewDecoder { ansi: true, ..Default::default() },
+        }
+    }
+}
+
+impl Scrollback {
+    pub fn new() -> Scrollback {
+        Scrollback::default()
+    }
+
+    pub fn push(&mut self, chunk: Chunk) {
+        if let Some(mut held) = self.held.take() {
+            held.push(chunk);
+            // what would scroll out right after resuming goes through the view now, so the
+            // held data stays within the bounds of the scrollback and is still spilled
+            while held.lines > self.max_lines || held.bytes > MAX_HELD_BYTES {
+                let Some(chunk) = held.pop() else {
+                    break;
+                };
+                self.append(chunk);
+                self.trim(true);
+            }
+            self.held = Some(held);
+            return;
+        }
+        self.append(chunk);
+        self.trim(true);
+    }
+
+    /// Adds a message from the application itself
+    pub fn push_local(&mut self, text: &str) {
+        self.push(Chunk::new(Direction::Local, text.as_bytes().to_vec()));
+    }
+
+    /// Records that the port was opened at `at`, the origin of `TimestampMode::SinceConnect`
+    pub fn mark_connected(&mut self, at: DateTime<Local>) {
+        self.connects.push(at);
+        self.decoder.connected_at = Some(at);
+    }
+
+    pub fn mode(&self) -> ViewMode {
+        self.decoder.mode
+    }
+
+    /// Switches the view mode, re-rendering the whole history
+    pub fn set_mode(&mut self, mode: ViewMode) {
+        if mode != self.decoder.mode {
+            self.rerender(mode, self.decoder.timestamps);
+        }
+    }
+
+    pub fn timestamps(&self) -> TimestampMode {
+        self.decoder.timestamps
+    }
+
+    /// Switches the line timestamps, re-rendering the whole history
+    pub fn set_timestamps(&mut self, timestamps: TimestampMode) {
+        if timestamps != self.decoder.timestamps {
+            self.rerender(self.decoder.mode, timestamps);
+        }
+    }
+
+    pub fn ansi(&self) -> bool {
+        self.decoder.ansi
+    }
+
+    /// Follows or shows escaped the ANSI sequences of text mode, re-rendering the whole
+    /// history
+    pub fn set_ansi(&mut self, ansi: bool) {
+        if ansi != self.decoder.ansi {
+            self.decoder.ansi = ansi;
+            self.rerender(self.decoder.mode, self.decoder.timestamps);
+        }
+    }
+
+    pub fn cr_newline(&self) -> bool {
+        self.decoder.text.cr_newline
+    }
+
+    /// Ends lines at carriage returns not followed by a line feed, re-rendering the whole
+    /// history
+    pub fn set_cr_newline(&mut self, cr_newline: bool) {
+        if cr_newline != self.decoder.text.cr_newline {
+            self.decoder.text.cr_newline = cr_newline;
+            self.rerender(self.decoder.mode, self.decoder.timestamps);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.held.is_some()
+    }
+
+    /// Freezes the view, what arrives meanwhile is shown when it resumes. Only as much is held
+    /// back as the scrollback keeps, older data goes into the view while paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        match (paused, self.held.take()) {
+            (true, held) => self.held = Some(held.unwrap_or_default()),
+            (false, Some(held)) => {
+                for chunk in held.chunks {
+                    self.append(chunk);
+                    self.trim(true);
+                }
+            }
+            (false, None) => {}
+        }
+    }
+
+    /// Number of chunks held back while paused
+    pub fn held_chunks(&self) -> usize {
+        self.held.as_ref().map_or(0, |held| held.chunks.len())
+    }
+
+    pub fn max_lines(&self) -> usize {
+        self.max_lines
+    }
+
+    /// Bounds the scrollback to `max_lines` complete lines, dropping the oldest
+    pub fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = max_lines.max(1);
+        self.trim(true);
+    }
+
+    /// Starts appending dropped lines to a new file in `dir`, returning its path
+    pub fn spill_to(&mut self, dir: &Path) -> Result<PathBuf, String> {
+        self.stop_spill();
+        let spill = Spill::create(dir)?;
+        let path = spill.path.clone();
+        self.spill = Some(spill);
+        Ok(path)
+    }
+
+    pub fn stop_spill(&mut self) {
+        if let Some(mut spill) = self.spill.take() {
+            let _ = spill.file.flush();
+        }
+    }
+
+    pub fn spill_path(&self) -> Option<&Path> {
+        self.spill.as_ref().map(|spill| spill.path.as_path())
+    }
+
+    /// The error that stopped spilling, once
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    /// Number of lines in the view, including the one being written
+    pub fn line_count(&self) -> usize {
+        let tail_lines = if self.tail.is_empty() {
+            0
+        } else {
+            self.tail.matches('\n').count() + usize::from(!self.tail.ends_with('\n'))
+        };
+        self.lines.len() + tail_lines
+    }
+
+    /// Number of the lines complete in the view, the lines after them are still being written
+    pub fn complete_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Number of line 0 of the view, counting the lines dropped before it. A line keeps its
+    /// number until the view is rendered again, which changes `generation`
+    pub fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Line `index` of the view, without its line break, with the SGR sequences that style it
+    /// when following ANSI sequences
+    pub fn line(&self, index: usize) -> Option<&str> {
+        match self.lines.get(index) {
+            Some(line) => Some(line),
+            None if index < self.line_count() => {
+                self.tail.split('\n').nth(index - self.lines.len())
+            }
+            None => None,
+        }
+    }
+
+    /// The whole view as one string, without escape sequences
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            text.push_str(&strip(line));
+            text.push('\n');
+        }
+        text.push_str(&strip(&self.tail));
+        text
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.chunk_lines.clear();
+        self.lines.clear();
+        self.tail.clear();
+        self.first_line = 0;
+        if let Some(held) = self.held.as_mut() {
+            *held = Held::default();
+        }
+        // the current connection still counts for the timestamps
+        self.connects = self.connects.last().copied().into_iter().collect();
+        self.rerender(self.decoder.mode, self.decoder.timestamps);
+    }
+
+    /// Renders the view from scratch
+    pub fn render(&self) -> String {
+        let mut copy = Scrollback {
+            chunks: self.chunks.clone(),
+            max_lines: self.max_lines,
+            connects: self.connects.clone(),
+            ..Default::default()
+        };
+        copy.decoder.ansi = self.decoder.ansi;
+        copy.decoder.text.cr_newline = self.decoder.text.cr_newline;
+        copy.rerender(self.decoder.mode, self.decoder.timestamps);
+        copy.text()
+    }
+
+    /// Renders `chunk` into the view and moves the lines it completed out of the tail
+    fn append(&mut self, chunk: Chunk) {
+        self.chunk_lines.push_back(self.first_line + self.lines.len());
+        self.decoder.decode(&chunk, &mut self.tail);
+        self.chunks.push_back(chunk);
+
+        // a hex row still being filled stays in the tail to be rewritten
+        let keep_from = self.decoder.pending_row_start().unwrap_or(self.tail.len());
+        let Some(end) = self.tail[..keep_from].rfind('\n') else {
+            return;
+        };
+        let rest = self.tail.split_off(end + 1);
+        let done = std::mem::replace(&mut self.tail, rest);
+        self.decoder.shift(end + 1);
+        self.lines.extend(done[..end].split('\n').map(str::to_string));
+    }
+
+    /// Drops the oldest lines beyond `max_lines`, and the chunks no longer shown
+    fn trim(&mut self, spill: bool) {
+        let excess = self.lines.len().saturating_sub(self.max_lines);
+        if excess == 0 {
+            return;
+        }
+        let dropped = self.lines.drain(..excess);
+        if let (true, Some(file)) = (spill, self.spill.as_mut()) {
+            let mut result = Ok(());
+            for line in dropped {
+                result = writeln!(file.file, "{}", strip(&line));
+                if result.is_err() {
+                    break;
+                }
+            }
+            if let Err(e) = result.and_then(|_| file.file.flush()) {
+                self.error = Some(format!("Stopped spilling to {}: {}", file.path.display(), e));
+                self.spill = None;
+            }
+        } else {
+            drop(dropped);
+        }
+        self.first_line += excess;
+
+        // a chunk is forgotten once the chunk after it starts on a dropped line
+        while self.chunk_lines.len() > 1 && self.chunk_lines[1] < self.first_line {
+            self.chunk_lines.pop_front();
+            self.chunks.pop_front();
+        }
+    }
+
+    /// Renders the chunks still shown from scratch
+    fn rerender(&mut self, mode: ViewMode, timestamps: TimestampMode) {
+        let chunks = std::mem::take(&mut self.chunks);
+        self.chunk_lines.clear();
+        self.lines.clear();
+        self.tail.clear();
+        self.first_line = 0;
+        self.generation += 1;
+        let ansi = self.decoder.ansi;
+        let cr_newline = self.decoder.text.cr_newline;
+        self.decoder = ViewDecoder { ansi, ..ViewDecoder::new(mode, timestamps) };
+        self.decoder.text.cr_newline = cr_newline;
+
+        let mut connect = 0;
+        for chunk in chunks {
+            while connect < self.connects.len() && self.connects[connect] <= chunk.at {
+                self.decoder.connected_at = Some(self.connects[connect]);
+                connect += 1;
+            }
+            self.append(chunk);
+        }
+        if let Some(&at) = self.connects.last() {
+            self.decoder.connected_at = Some(at);
+        }
+        // lines rendered again that had been dropped are not spilled twice
+        self.trim(false);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rx(bytes: &[u8]) -> Chunk {
+        Chunk::new(Direction::Rx, bytes.to_vec())
+    }
+
+    #[test]
+    fn split_and_invalid_sequences() {
+        let mut scrollback = Scrollback::new();
+        scrollback.set_ansi(false);
+        // "µ" is 0xC2 0xB5, split over two reads
+        scrollback.push(rx(b"temp 21.5 \xC2"));
+        scrollback.push(rx(b"\xB5s\r\n"));
+        scrollback.push(rx(b"frame \xFF\xFE\x00\x1B[0m\n"));
+        assert_eq!(scrollback.text(), "temp 21.5 µs\nframe \\xFF\\xFE\\x00\\x1B[0m\n");
+        assert_eq!(scrollback.render(), scrollback.text());
+    }
+
+    #[test]
+    fn direction_change_flushes_partial_sequence() {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(rx(b"ok \xE2\x82"));
+        scrollback.push_local("\n");
+        assert_eq!(scrollback.text(), "ok \\xE2\\x82\n");
+        scrollback.clear();
+        assert!(scrollback.text().is_empty());
+        assert!(scrollback.chunks.is_empty());
+    }
+
+    #[test]
+    fn pause_holds_chunks() {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(rx(b"one\n"));
+        scrollback.set_paused(true);
+        scrollback.push(rx(b"two\n"));
+        scrollback.push_local("three\n");
+        assert_eq!(scrollback.text(), "one\n");
+        assert_eq!(scrollback.held_chunks(), 2);
+        scrollback.set_paused(false);
+        assert!(!scrollback.is_paused());
+        assert_eq!(scrollback.text(), "one\ntwo\nthree\n");
+    }
+
+    #[test]
+    fn held_chunks_are_bounded() {
+        let dir = std::env::temp_dir().join(format!("arrakis-held-{}", std::process::id()));
+        let mut scrollback = Scrollback::new();
+        scrollback.set_max_lines(3);
+        let path = scrollback.spill_to(&dir).unwrap();
+        scrollback.set_paused(true);
+        for line in ["one\n", "two\n", "three\n", "four\n", "five\n", "six\n"] {
+            scrollback.push(rx(line.as_bytes()));
+        }
+        assert_eq!(scrollback.held_chunks(), 3);
+        assert_eq!(scrollback.text(), "one\ntwo\nthree\n");
+
+        scrollback.set_paused(false);
+        assert_eq!(scrollback.text(), "four\nfive\nsix\n");
+        scrollback.stop_spill();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\nthree\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn hex_rows_fill_across_chunks() {
+        let mut scrollback = Scrollback::new();
+        scrollback.set_mode(ViewMode::Hex);
+        scrollback.push(rx(b"Hello, "));
+        assert_eq!(
+            scrollback.text(),
+            "RX 00000000  48 65 6C 6C 6F 2C 20                              |Hello, |\n"
+        );
+        scrollback.push(rx(b"world!\r\n\x00\xFF\x1B"));
+        let text = scrollback.text();
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0],
+            "RX 00000000  48 65 6C 6C 6F 2C 20 77  6F 72 6C 64 21 0D 0A 00  |Hello, world!...|"
+        );
+        assert_eq!(rows[1], "RX 00000010  FF 1B                                             |..|");
+        assert_eq!(scrollback.render(), scrollback.text());
+    }
+
+    #[test]
+    fn mode_switch_keeps_history() {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(rx(b"ok\x1B[0m\r\n"));
+        scrollback.push(Chunk::new(Direction::Tx, b"go\n".to_vec()));
+
+        scrollback.set_mode(ViewMode::Mixed);
+        assert_eq!(scrollback.text(), "ok<0x1B>[0m<0x0D><0x0A>\ngo<0x0A>\n");
+
+        scrollback.set_mode(ViewMode::Hex);
+        assert!(scrollback.text().contains("\nTX 00000000  67 6F 0A"));
+
+        scrollback.set_mode(ViewMode::Text);
+        assert_eq!(scrollback.text(), "ok\ngo\n");
+        assert_eq!(scrollback.chunks.len(), 2);
+        assert_eq!(ViewMode::from_name("HEX"), Some(ViewMode::Hex));
+    }
+
+    #[test]
+    fn ansi_sequences() {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(rx(b"\x1B[1;32muart:~$ \x1B[m"));
+        scrollback.push(rx(b"kernel ve"));
+        // the shell takes back what was typed and redraws it
+        scrollback.push(rx(b"\x08\x08\x1B[Kuptime\r\n\x1B[31mE: "));
+        scrollback.push(rx(b"fault\x1B[0m\r\n"));
+        assert_eq!(scrollback.text(), "uart:~$ kernel uptime\nE: fault\n");
+        assert_eq!(
+            scrollback.line(0),
+            Some("\x1B[0;1;38;2;13;188;121muart:~$ \x1B[0mkernel uptime")
+        );
+        assert_eq!(scrollback.line(1), Some("\x1B[0;38;2;205;49;49mE: fault"));
+        assert_eq!(scrollback.render(), scrollback.text());
+
+        scrollback.set_ansi(false);
+        assert_eq!(scrollback.line(1), Some("\\x1B[31mE: fault\\x1B[0m"));
+    }
+
+    #[test]
+    fn carriage_return_ends_lines() {
+        for (ansi, plain) in [(true, "T=21.6\nT=21.7\n"), (false, "T=21.5T=21.6\nT=21.7\n")] {
+            let mut scrollback = Scrollback::new();
+            scrollback.set_ansi(ansi);
+            scrollback.push(rx(b"T=21.5\rT=21.6\r"));
+            scrollback.push(rx(b"\nT=21.7\r\n"));
+            assert_eq!(scrollback.text(), plain);
+            scrollback.set_cr_newline(true);
+            assert_eq!(scrollback.text(), "T=21.5\nT=21.6\nT=21.7\n");
+            assert_eq!(scrollback.render(), scrollback.text());
+        }
+    }
+
+    #[test]
+    fn split_lines_are_edited() {
+        let mut lines = LineSplitter::new();
+        assert!(lines.push(&rx(b"kernel ve")).is_empty());
+        let texts = |lines: Vec<TextLine>| -> Vec<String> {
+            lines
+                .into_iter()
+                .map(|line| line.text)
+                .collect()
+        };
+        let pushed = lines.push(&rx(b"\x08\x08\x1B[Kuptime\r\nT=1\rT=2\r"));
+        assert_eq!(texts(pushed), ["kernel uptime"]);
+        lines.set_cr_newline(true);
+        assert_eq!(texts(lines.push(&rx(b"\nT=3\r"))), ["T=2", "T=3"]);
+        assert_eq!(lines.finish(), None);
+    }
+
+    #[test]
+    fn timestamps_at_line_starts() {
+        let connected_at = Local::now();
+        let chunk = |direction, micros, bytes: &[u8]| Chunk {
+            at: connected_at + chrono::Duration::microseconds(micros),
+            direction,
+            bytes: bytes.to_vec(),
+        };
+        let mut scrollback = Scrollback::new();
+        scrollback.mark_connected(connected_at);
+        scrollback.set_timestamps(TimestampMode::SinceConnect);
+        scrollback.push(chunk(Direction::Rx, 1_500, b"boot\nre"));
+        scrollback.push(chunk(Direction::Rx, 2_000_250, b"ady\n"));
+        scrollback.push_local("\nnote\n");
+        assert_eq!(scrollback.text(), "[+   0.001500] boot\n[+   0.001500] ready\n\nnote\n");
+
+        scrollback.set_timestamps(TimestampMode::Delta);
+        scrollback.push(chunk(Direction::Tx, 2_500_250, b"go\n"));
+        let text = scrollback.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "[Δ   0.000000] boot");
+        assert_eq!(lines[4], "[Δ   2.498750] go");
+
+        scrollback.set_mode(ViewMode::Hex);
+        assert!(scrollback.text().starts_with("[Δ   0.000000] RX 00000000  62 6F 6F 74"));
+
+        scrollback.set_timestamps(TimestampMode::Off);
+        scrollback.set_mode(ViewMode::Text);
+        assert_eq!(scrollback.text(), "boot\nready\n\nnote\ngo\n");
+    }
+
+    #[test]
+    fn long_lines_are_broken() {
+        for ansi in [false, true] {
+            let mut scrollback = Scrollback::new();
+            scrollback.set_ansi(ansi);
+            scrollback.set_max_lines(2);
+            scrollback.push(rx(&[b'a'; MAX_LINE_LEN * 5 + 10]));
+            assert_eq!(scrollback.line_count(), 3);
+            assert_eq!(scrollback.line(0).unwrap().len(), MAX_LINE_LEN);
+            assert_eq!(scrollback.line(2), Some("a".repeat(10).as_str()));
+            // the partial line fills up first
+            scrollback.push(rx(&[b'b'; MAX_LINE_LEN]));
+            assert_eq!(scrollback.line_count(), 3);
+            assert_eq!(&scrollback.line(1).unwrap()[8..12], "aabb");
+            assert_eq!(scrollback.line(2), Some("b".repeat(10).as_str()));
+        }
+    }
+
+    #[test]
+    fn cursor_moves_stay_within_a_line() {
+        let input = format!("{}x\n", "\x1b[65535C".repeat(1000));
+        let mut scrollback = Scrollback::new();
+        scrollback.push(rx(input.as_bytes()));
+        assert_eq!(scrollback.line(0).unwrap().chars().count(), MAX_LINE_LEN);
+        assert!(scrollback.line(0).unwrap().ends_with(" x"));
+
+        let mut lines = LineSplitter::new();
+        let pushed = lines.push(&rx(input.as_bytes()));
+        assert!(pushed.iter().all(|line| line.text.chars().count() <= MAX_LINE_LEN));
+    }
+
+    #[test]
+    fn bounded_lines_spill_to_disk() {
+        let dir = std::env::temp_dir().join(format!("arrakis-spill-{}", std::process::id()));
+        let mut scrollback = Scrollback::new();
+        scrollback.set_max_lines(3);
+        let path = scrollback.spill_to(&dir).unwrap();
+
+        scrollback.push(rx(b"one\ntwo\nthr"));
+        scrollback.push(rx(b"ee\nfour\n"));
+        scrollback.push(rx(b"five\nsi"));
+        assert_eq!(scrollback.line_count(), 4);
+        assert_eq!(scrollback.line(0), Some("three"));
+        assert_eq!(scrollback.line(3), Some("si"));
+        assert_eq!(scrollback.line(4), None);
+        // the first chunk still has the start of "three"
+        assert_eq!(scrollback.chunks.len(), 3);
+        scrollback.push(rx(b"x\nseven\n"));
+        assert_eq!(scrollback.chunks.len(), 3);
+
+        scrollback.set_mode(ViewMode::Mixed);
+        assert_eq!(scrollback.text(), "five<0x0A>\nsix<0x0A>\nseven<0x0A>\n");
+
+        scrollback.stop_spill();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\nthree\nfour\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
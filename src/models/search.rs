@@ -0,0 +1,382 @@
+//! Find and filter of the terminal, over the lines of the scrollback without their escape
+//! sequences. Lines are scanned as they complete, so the matches keep up with the capture.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::models::ansi::strip;
+use crate::models::highlight::{ runs, Rgb, Style };
+use crate::models::scrollback::Scrollback;
+
+/// Complete lines scanned per frame, a new query over a long scrollback takes a few frames
+const SCAN_PER_FRAME: usize = 20_000;
+
+/// What the find text is matched as
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub text: String,
+    /// The text is a regex, otherwise it is found as typed
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+impl Query {
+    /// The regex finding the query, none for an empty query
+    pub fn matcher(&self) -> Result<Option<Regex>, String> {
+        if self.text.is_empty() {
+            return Ok(None);
+        }
+        let pattern = if self.regex { self.text.clone() } else { regex::escape(&self.text) };
+        let pattern = if self.case_sensitive { pattern } else { format!("(?i){}", pattern) };
+        Regex::new(&pattern).map(Some).map_err(|e| e.to_string())
+    }
+}
+
+/// Which lines the terminal shows while finding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Every line, the matches highlighted
+    #[default]
+    Off,
+    Matching,
+    NotMatching,
+}
+
+pub const FILTER_MODES: [FilterMode; 3] = [
+    FilterMode::Off,
+    FilterMode::Matching,
+    FilterMode::NotMatching,
+];
+
+impl FilterMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterMode::Off => "All lines",
+            FilterMode::Matching => "Matching",
+            FilterMode::NotMatching => "Not matching",
+        }
+    }
+}
+
+/// How matches are drawn, the selected line's stand out
+pub fn match_style(current: bool) -> Style {
+    let background = if current { Rgb([0xff, 0xb8, 0x6c]) } else { Rgb([0xf1, 0xfa, 0x8c]) };
+    Style {
+        foreground: Some(Rgb([0x28, 0x2a, 0x36])),
+        background: Some(background),
+        ..Default::default()
+    }
+}
+
+/// The find bar of the terminal, with the lines matching its query. Lines are numbered as
+/// in `Scrollback::first_line`.
+#[derive(Debug, Default)]
+pub struct Search {
+    /// The find bar is shown, the matches are only highlighted and filtered while it is
+    pub open: bool,
+    pub query: Query,
+    pub filter: FilterMode,
+    /// The query does not compile
+    pub error: Option<String>,
+    /// What `matcher` was built from, to notice edits
+    built: Option<Query>,
+    matcher: Option<Regex>,
+    /// Scrollback generation the line numbers belong to
+    generation: u64,
+    /// Complete lines before this one are scanned
+    scanned: usize,
+    /// Complete lines matching and not matching, in order
+    hits: VecDeque<usize>,
+    misses: VecDeque<usize>,
+    /// Lines still being written matching and not matching, scanned again every frame
+    tail_hits: Vec<usize>,
+    tail_misses: Vec<usize>,
+    /// The line selected by next and previous
+    current: Option<usize>,
+    /// The row of the view to scroll to, once
+    pub scroll_to: Option<usize>,
+}
+
+impl Search {
+    pub fn new() -> Search {
+        Search::default()
+    }
+
+    /// Finds and filters with a regex, the find bar is open and has a query
+    pub fn is_active(&self) -> bool {
+        self.open && self.matcher.is_some()
+    }
+
+    fn filtering(&self) -> bool {
+        self.is_active() && self.filter != FilterMode::Off
+    }
+
+    /// Scans the lines added since the last update, from scratch when the query changed or
+    /// the view was rendered again. Returns whether lines are left to scan.
+    pub fn update(&mut self, scrollback: &Scrollback) -> bool {
+        if self.built.as_ref() != Some(&self.query) {
+            (self.matcher, self.error) = match self.query.matcher() {
+                Ok(matcher) => (matcher, None),
+                Err(e) => (None, Some(e)),
+            };
+            self.built = Some(self.query.clone());
+            self.reset(scrollback);
+        }
+        if self.generation != scrollback.generation() {
+            self.reset(scrollback);
+        }
+        let Some(matcher) = self.matcher.clone().filter(|_| self.open) else {
+            return false;
+        };
+
+        // lines dropped from the scrollback are dropped here too
+        let first = scrollback.first_line();
+        for lines in [&mut self.hits, &mut self.misses] {
+            let dropped = lines.partition_point(|&line| line < first);
+            lines.drain(..dropped);
+        }
+        if self.current.is_some_and(|line| line < first) {
+            self.current = None;
+        }
+
+        let complete = first + scrollback.complete_lines();
+        let start = self.scanned.max(first);
+        let end = complete.min(start + SCAN_PER_FRAME);
+        for line in start..end {
+            let text = scrollback.line(line - first).unwrap_or_default();
+            match matcher.is_match(&strip(text)) {
+                true => self.hits.push_back(line),
+                false => self.misses.push_back(line),
+            }
+        }
+        self.scanned = end;
+
+        self.tail_hits.clear();
+        self.tail_misses.clear();
+        if end == complete {
+            for line in complete..first + scrollback.line_count() {
+                let text = scrollback.line(line - first).unwrap_or_default();
+                match matcher.is_match(&strip(text)) {
+                    true => self.tail_hits.push(line),
+                    false => self.tail_misses.push(line),
+                }
+            }
+        }
+        end < complete
+    }
+
+    fn reset(&mut self, scrollback: &Scrollback) {
+        self.generation = scrollback.generation();
+        self.scanned = 0;
+        self.hits.clear();
+        self.misses.clear();
+        self.tail_hits.clear();
+        self.tail_misses.clear();
+        self.current = None;
+    }
+
+    /// Number of lines matching the query
+    pub fn match_count(&self) -> usize {
+        self.hits.len() + self.tail_hits.len()
+    }
+
+    /// Position of the selected line among the matching ones, from 1
+    pub fn current_position(&self) -> Option<usize> {
+        let current = self.current?;
+        match self.hits.binary_search(&current) {
+            Ok(index) => Some(index + 1),
+            Err(_) => {
+                let index = self.tail_hits.binary_search(&current).ok()?;
+                Some(self.hits.len() + index + 1)
+            }
+        }
+    }
+
+    /// Whether `line` is the one selected by next and previous
+    pub fn is_current(&self, line: usize) -> bool {
+        self.current == Some(line)
+    }
+
+    /// Number of rows the terminal shows
+    pub fn row_count(&self, scrollback: &Scrollback) -> usize {
+        match self.filter {
+            _ if !self.filtering() => scrollback.line_count(),
+            FilterMode::NotMatching => self.misses.len() + self.tail_misses.len(),
+            _ => self.hits.len() + self.tail_hits.len(),
+        }
+    }
+
+    /// Index in the view of the line shown on `row`
+    pub fn row_line(&self, scrollback: &Scrollback, row: usize) -> Option<usize> {
+        let (lines, tail) = match self.filter {
+            _ if !self.filtering() => return Some(row),
+            FilterMode::NotMatching => (&self.misses, &self.tail_misses),
+            _ => (&self.hits, &self.tail_hits),
+        };
+        let line = match lines.get(row) {
+            Some(&line) => line,
+            None => *tail.get(row - lines.len())?,
+        };
+        Some(line - scrollback.first_line())
+    }
+
+    /// The row showing line `line`, if it is shown
+    fn line_row(&self, scrollback: &Scrollback, line: usize) -> Option<usize> {
+        let (lines, tail) = match self.filter {
+            _ if !self.filtering() => return line.checked_sub(scrollback.first_line()),
+            FilterMode::NotMatching => (&self.misses, &self.tail_misses),
+            _ => (&self.hits, &self.tail_hits),
+        };
+        match lines.binary_search(&line) {
+            Ok(row) => Some(row),
+            Err(_) => tail.binary_search(&line).ok().map(|row| lines.len() + row),
+        }
+    }
+
+    /// Selects the next matching line, after the last one from the first, and scrolls to it
+    pub fn next(&mut self, scrollback: &Scrollback) {
+        let mut matches = self.hits.iter().chain(&self.tail_hits);
+        let next = match self.current {
+            Some(current) => matches.find(|&&line| line > current),
+            None => None,
+        };
+        self.select(scrollback, next.or(self.hits.front()).or(self.tail_hits.first()).copied());
+    }
+
+    /// Selects the previous matching line, before the first one from the last, and scrolls
+    /// to it
+    pub fn previous(&mut self, scrollback: &Scrollback) {
+        let mut matches = self.hits.iter().chain(&self.tail_hits).rev();
+        let previous = match self.current {
+            Some(current) => matches.clone().find(|&&line| line < current),
+            None => None,
+        };
+        self.select(scrollback, previous.or(matches.next()).copied());
+    }
+
+    fn select(&mut self, scrollback: &Scrollback, line: Option<usize>) {
+        let Some(line) = line else {
+            return;
+        };
+        self.current = Some(line);
+        self.scroll_to = self.line_row(scrollback, line);
+    }
+
+    /// Scrolls to line `line`, if it is shown
+    pub fn reveal(&mut self, scrollback: &Scrollback, line: usize) {
+        self.scroll_to = self.line_row(scrollback, line);
+    }
+
+    /// `line` split into runs of one style covering all of it, the matches of the query
+    /// drawn with `match_style`
+    pub fn spans(&self, line: &str, current: bool) -> Vec<(Range<usize>, Style)> {
+        let mut styles = vec![Style::default(); line.len()];
+        if let Some(matcher) = self.matcher.as_ref().filter(|_| self.open) {
+            for found in matcher.find_iter(line) {
+                styles[found.range()].fill(match_style(current));
+            }
+        }
+        runs(line, styles)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::scrollback::{ Chunk, Direction };
+
+    fn scrollback(text: &str) -> Scrollback {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(Chunk::new(Direction::Rx, text.as_bytes().to_vec()));
+        scrollback
+    }
+
+    fn search(text: &str) -> Search {
+        let mut search = Search::new();
+        search.open = true;
+        search.query.text = text.to_string();
+        search
+    }
+
+    #[test]
+    fn plain_regex_and_case() {
+        let query = Query { text: "a.b".to_string(), ..Default::default() };
+        let matcher = query.matcher().unwrap().unwrap();
+        assert!(matcher.is_match("xA.By"));
+        assert!(!matcher.is_match("aXb"));
+
+        let query = Query { text: "a.b".to_string(), regex: true, case_sensitive: true };
+        let matcher = query.matcher().unwrap().unwrap();
+        assert!(matcher.is_match("aXb"));
+        assert!(!matcher.is_match("AXB"));
+
+        let query = Query { text: "(".to_string(), regex: true, ..Default::default() };
+        assert!(query.matcher().is_err());
+        assert!(Query::default().matcher().unwrap().is_none());
+    }
+
+    #[test]
+    fn navigate_and_filter() {
+        let mut scrollback = scrollback("boot\n\x1b[31mERROR\x1b[0m 1\nok\nerror 2\nerr");
+        let mut search = search("error");
+        assert!(!search.update(&scrollback));
+        assert_eq!(search.match_count(), 2);
+        assert_eq!(search.row_count(&scrollback), 5);
+
+        search.next(&scrollback);
+        assert_eq!(search.current_position(), Some(1));
+        assert_eq!(search.scroll_to, Some(1));
+        search.next(&scrollback);
+        search.next(&scrollback);
+        assert_eq!(search.current_position(), Some(1));
+        search.previous(&scrollback);
+        assert_eq!(search.current_position(), Some(2));
+
+        search.filter = FilterMode::Matching;
+        assert_eq!(search.row_count(&scrollback), 2);
+        assert_eq!(search.row_line(&scrollback, 1), Some(3));
+        search.previous(&scrollback);
+        assert_eq!(search.scroll_to, Some(0));
+
+        // the line being written matches once it is long enough, and stays once complete
+        scrollback.push(Chunk::new(Direction::Rx, b"or 3\n".to_vec()));
+        search.update(&scrollback);
+        assert_eq!(search.row_count(&scrollback), 3);
+        search.filter = FilterMode::NotMatching;
+        assert_eq!(search.row_count(&scrollback), 2);
+        assert_eq!(search.row_line(&scrollback, 1), Some(2));
+
+        search.open = false;
+        assert_eq!(search.row_count(&scrollback), scrollback.line_count());
+    }
+
+    #[test]
+    fn follows_dropped_and_rendered_lines() {
+        let mut scrollback = scrollback("a1\nb\na2\nb\na3\n");
+        let mut search = search("a");
+        search.update(&scrollback);
+        assert_eq!(search.match_count(), 3);
+
+        scrollback.set_max_lines(2);
+        search.update(&scrollback);
+        assert_eq!(search.match_count(), 1);
+        search.filter = FilterMode::Matching;
+        assert_eq!(search.row_line(&scrollback, 0), Some(1));
+
+        scrollback.clear();
+        search.update(&scrollback);
+        assert_eq!(search.match_count(), 0);
+    }
+
+    #[test]
+    fn match_spans() {
+        let mut search = search("ok");
+        search.update(&Scrollback::new());
+        let spans = search.spans("ok, ok", false);
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0], (0..2, match_style(false)));
+        assert_eq!(spans[1], (2..4, Style::default()));
+    }
+}
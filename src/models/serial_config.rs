@@ -0,0 +1,181 @@
+//! Serial line configuration, i.e. baud rate, framing, flow control and read timeout
+
+use std::time::Duration;
+
+use serde::{ Deserialize, Serialize };
+use serialport::{ DataBits, FlowControl, Parity, SerialPortBuilder, StopBits };
+
+/// Baud rates offered in the connection panel, any other rate may be typed in directly
+pub const STANDARD_BAUD_RATES: [u32; 16] = [
+    300, 1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400, 460800, 500000, 921600,
+    1000000, 2000000, 3000000,
+];
+
+pub const DATA_BITS: [DataBits; 4] = [
+    DataBits::Five,
+    DataBits::Six,
+    DataBits::Seven,
+    DataBits::Eight,
+];
+pub const PARITIES: [Parity; 3] = [Parity::None, Parity::Odd, Parity::Even];
+pub const STOP_BITS: [StopBits; 2] = [StopBits::One, StopBits::Two];
+pub const FLOW_CONTROLS: [FlowControl; 3] = [
+    FlowControl::None,
+    FlowControl::Software,
+    FlowControl::Hardware,
+];
+
+/// Everything needed to open a serial port besides its name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+    /// Read timeout in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: 460800,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            timeout_ms: 100,
+        }
+    }
+}
+
+impl SerialConfig {
+    pub fn new() -> SerialConfig {
+        SerialConfig::default()
+    }
+
+    /// Returns a `serialport` builder for `port_name` carrying every line setting
+    pub fn builder(&self, port_name: &str) -> SerialPortBuilder {
+        serialport
+            ::new(port_name, self.baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .flow_control(self.flow_control)
+            .timeout(Duration::from_millis(self.timeout_ms))
+    }
+
+    /// Short form of the line settings, i.e. "115200 8N1"
+    pub fn summary(&self) -> String {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+        };
+        let flow = match self.flow_control {
+            FlowControl::None => "",
+            FlowControl::Software => " XON/XOFF",
+            FlowControl::Hardware => " RTS/CTS",
+        };
+        format!(
+            "{} {}{}{}{}",
+            self.baud_rate,
+            u8::from(self.data_bits),
+            parity,
+            u8::from(self.stop_bits),
+            flow
+        )
+    }
+}
+
+/// The line settings a device profile sets, the ones it leaves out stay as they are
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineOverrides {
+    pub baud_rate: Option<u32>,
+    pub data_bits: Option<DataBits>,
+    pub parity: Option<Parity>,
+    pub stop_bits: Option<StopBits>,
+    pub flow_control: Option<FlowControl>,
+    pub timeout_ms: Option<u64>,
+}
+
+impl LineOverrides {
+    /// Sets the fields of `config` the overrides name, returns whether any changed
+    pub fn apply(&self, config: &mut SerialConfig) -> bool {
+        let before = config.clone();
+        if let Some(baud_rate) = self.baud_rate {
+            config.baud_rate = baud_rate;
+        }
+        if let Some(data_bits) = self.data_bits {
+            config.data_bits = data_bits;
+        }
+        if let Some(parity) = self.parity {
+            config.parity = parity;
+        }
+        if let Some(stop_bits) = self.stop_bits {
+            config.stop_bits = stop_bits;
+        }
+        if let Some(flow_control) = self.flow_control {
+            config.flow_control = flow_control;
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            config.timeout_ms = timeout_ms;
+        }
+        *config != before
+    }
+
+    /// The settings named, i.e. "115200 baud, Even parity"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(baud_rate) = self.baud_rate {
+            parts.push(format!("{} baud", baud_rate));
+        }
+        if let Some(data_bits) = self.data_bits {
+            parts.push(format!("{} data bits", u8::from(data_bits)));
+        }
+        if let Some(parity) = self.parity {
+            parts.push(format!("{} parity", parity));
+        }
+        if let Some(stop_bits) = self.stop_bits {
+            parts.push(format!("{} stop bits", u8::from(stop_bits)));
+        }
+        if let Some(flow_control) = self.flow_control {
+            parts.push(format!("{} flow control", flow_control));
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            parts.push(format!("{} ms timeout", timeout_ms));
+        }
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn summary() {
+        let mut config = SerialConfig::new();
+        assert_eq!(config.summary(), "460800 8N1");
+
+        config.baud_rate = 115200;
+        config.data_bits = DataBits::Seven;
+        config.parity = Parity::Even;
+        config.stop_bits = StopBits::Two;
+        config.flow_control = FlowControl::Hardware;
+        assert_eq!(config.summary(), "115200 7E2 RTS/CTS");
+    }
+
+    #[test]
+    fn overrides_keep_other_settings() {
+        let mut config = SerialConfig { parity: Parity::Odd, ..SerialConfig::new() };
+        let overrides = LineOverrides { baud_rate: Some(115200), ..Default::default() };
+        assert!(overrides.apply(&mut config));
+        assert_eq!(config.summary(), "115200 8O1");
+        assert!(!overrides.apply(&mut config));
+        assert_eq!(overrides.summary(), "115200 baud");
+    }
+}
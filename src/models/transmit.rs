@@ -0,0 +1,102 @@
+//! Transmit settings, the line typed by the user and how it is framed on the wire
+
+
+/// Terminator appended to every line sent to the port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    None,
+    Cr,
+    #[default]
+    Lf,
+    CrLf,
+}
+
+pub const LINE_ENDINGS: [LineEnding; 4] = [
+    LineEnding::None,
+    LineEnding::Cr,
+    LineEnding::Lf,
+    LineEnding::CrLf,
+];
+
+impl LineEnding {
+    pub fn bytes(&self) -> &'static [u8] {
+        match self {
+            LineEnding::None => b"",
+            LineEnding::Cr => b"\r",
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineEnding::None => "None",
+            LineEnding::Cr => "CR",
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CR+LF",
+        }
+    }
+}
+
+pub struct Transmit {
+    /// The line being typed
+    pub input: String,
+    pub line_ending: LineEnding,
+    /// Show sent lines in the terminal
+    pub local_echo: bool,
+    /// Bytes written to the port since it was opened
+    pub bytes_sent: usize,
+    /// Ctrl+R search of the history, while searching
+    pub search: Option<HistorySearch>,
+    /// Completions shown after a Tab that matched more than one
+    pub candidates: Vec<String>,
+}
+
+/// Reverse search of the history
+#[derive(Debug, Clone, Default)]
+pub struct HistorySearch {
+    pub query: String,
+    /// Index of the entry matching the query
+    pub found: Option<usize>,
+}
+
+impl Default for Transmit {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            line_ending: LineEnding::default(),
+            local_echo: true,
+            bytes_sent: 0,
+            search: None,
+            candidates: Vec::new(),
+        }
+    }
+}
+
+impl Transmit {
+    pub fn new() -> Transmit {
+        Transmit::default()
+    }
+
+    /// The bytes written to the port for `line`
+    pub fn frame(&self, line: &[u8]) -> Vec<u8> {
+        let mut bytes = line.to_vec();
+        bytes.extend_from_slice(self.line_ending.bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame() {
+        let mut transmit = Transmit::new();
+        assert_eq!(transmit.frame(b"reg 0x10"), b"reg 0x10\n");
+        transmit.line_ending = LineEnding::CrLf;
+        assert_eq!(transmit.frame(b"reg 0x10"), b"reg 0x10\r\n");
+        transmit.line_ending = LineEnding::None;
+        assert_eq!(transmit.frame(b""), b"");
+    }
+}
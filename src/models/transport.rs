@@ -0,0 +1,454 @@
+//! Transports a session talks over: a local serial port, a remote one on an RFC 2217 server,
+//! or a network endpoint such as a ser2net or telnet server, a simulator listening on TCP or
+//! UDP, or a Unix domain socket
+
+use std::io::{ self, Read, Write };
+use std::net::{ Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::time::Duration;
+
+use serde::{ Deserialize, Serialize };
+use serialport::SerialPort;
+
+use crate::models::rfc2217::Rfc2217;
+use crate::models::serial_config::SerialConfig;
+
+/// How long to wait for a TCP server to accept the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a write to a socket may block before it fails, so a peer that stopped reading
+/// cannot hang the caller
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often a TCP server checks whether it was asked to stop while waiting for a client
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+
+/// A byte stream to a device. Reads return after the read timeout of the line settings with
+/// `TimedOut` or `WouldBlock` when nothing arrived.
+pub trait Transport: Read + Write + Send {
+    /// A second handle on the same stream, the reader thread works on its own
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>>;
+
+    /// Applies new line settings to the open stream, only serial ports, local or remote, have
+    /// any
+    fn set_config(&mut self, _config: &SerialConfig) -> Result<(), String> {
+        Err("The connection has no line settings".to_string())
+    }
+
+    fn set_dtr(&mut self, _on: bool) -> Result<(), String> {
+        Err("The connection has no control lines".to_string())
+    }
+
+    fn set_rts(&mut self, _on: bool) -> Result<(), String> {
+        Err("The connection has no control lines".to_string())
+    }
+}
+
+impl Transport for Box<dyn SerialPort> {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        let port = SerialPort::try_clone(self.as_ref()).map_err(io::Error::from)?;
+        Ok(Box::new(port))
+    }
+
+    fn set_config(&mut self, config: &SerialConfig) -> Result<(), String> {
+        let result = self
+            .set_baud_rate(config.baud_rate)
+            .and_then(|_| self.set_data_bits(config.data_bits))
+            .and_then(|_| self.set_parity(config.parity))
+            .and_then(|_| self.set_stop_bits(config.stop_bits))
+            .and_then(|_| self.set_flow_control(config.flow_control));
+        result.map_err(|e| format!("Failed to change the line settings: {}", e))
+    }
+
+    fn set_dtr(&mut self, on: bool) -> Result<(), String> {
+        self.write_data_terminal_ready(on).map_err(|e| format!("Failed to set DTR: {}", e))
+    }
+
+    fn set_rts(&mut self, on: bool) -> Result<(), String> {
+        self.write_request_to_send(on).map_err(|e| format!("Failed to set RTS: {}", e))
+    }
+}
+
+impl Transport for TcpStream {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(TcpStream::try_clone(self)?))
+    }
+}
+
+#[cfg(unix)]
+impl Transport for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(std::os::unix::net::UnixStream::try_clone(self)?))
+    }
+}
+
+/// A UDP socket connected to one peer, every write is a datagram
+struct UdpTransport(UdpSocket);
+
+impl Read for UdpTransport {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self.0.recv(buffer) {
+            // an empty datagram is not the end of the stream, and a peer not listening yet is
+            // reported as refused, both are quiet as far as the reader is concerned
+            Ok(0) => Err(io::ErrorKind::TimedOut.into()),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                Err(io::ErrorKind::TimedOut.into())
+            }
+            result => result,
+        }
+    }
+}
+
+impl Write for UdpTransport {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.0.send(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for UdpTransport {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(UdpTransport(self.0.try_clone()?)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransportKind {
+    #[default]
+    Serial,
+    /// A serial port on a Telnet COM Port Control server
+    Rfc2217,
+    /// Connects to a TCP server, i.e. ser2net or a telnet port
+    TcpClient,
+    /// Waits for one client on a TCP port
+    TcpServer,
+    Udp,
+    Unix,
+}
+
+/// Network transports offered in the Connection tab, serial ports are listed on their own
+pub const NETWORK_KINDS: [TransportKind; 5] = [
+    TransportKind::Rfc2217,
+    TransportKind::TcpClient,
+    TransportKind::TcpServer,
+    TransportKind::Udp,
+    TransportKind::Unix,
+];
+
+impl TransportKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransportKind::Serial => "Serial",
+            TransportKind::Rfc2217 => "RFC 2217",
+            TransportKind::TcpClient => "TCP Client",
+            TransportKind::TcpServer => "TCP Server",
+            TransportKind::Udp => "UDP",
+            TransportKind::Unix => "Unix Socket",
+        }
+    }
+
+    /// Scheme of the endpoint URL, serial ports are named without one
+    pub fn scheme(&self) -> Option<&'static str> {
+        match self {
+            TransportKind::Serial => None,
+            TransportKind::Rfc2217 => Some("rfc2217"),
+            TransportKind::TcpClient => Some("tcp"),
+            TransportKind::TcpServer => Some("tcp-server"),
+            TransportKind::Udp => Some("udp"),
+            TransportKind::Unix => Some("unix"),
+        }
+    }
+
+    /// What the address is, for the hint of the Connection tab
+    pub fn address_hint(&self) -> &'static str {
+        match self {
+            TransportKind::Serial => "i.e. /dev/ttyUSB0",
+            TransportKind::Rfc2217 => "i.e. lab-server:4001",
+            TransportKind::TcpClient => "i.e. localhost:2000",
+            TransportKind::TcpServer => "i.e. 0.0.0.0:2000",
+            TransportKind::Udp => "i.e. 192.168.1.20:5000",
+            TransportKind::Unix => "i.e. /tmp/uart.sock",
+        }
+    }
+}
+
+/// Where a session connects to, written as a URL such as `tcp://localhost:2000`, a name
+/// without a scheme is a serial port
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Endpoint {
+    pub kind: TransportKind,
+    /// Serial port name, `host:port`, or socket path
+    pub address: String,
+    /// UDP port to receive on, 0 for any
+    pub local_port: u16,
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind.scheme() {
+            None => write!(f, "{}", self.address),
+            Some(scheme) => {
+                write!(f, "{}://{}", scheme, self.address)?;
+                if self.kind == TransportKind::Udp && self.local_port != 0 {
+                    write!(f, "?local={}", self.local_port)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Endpoint {
+    pub fn serial(port_name: &str) -> Endpoint {
+        Endpoint { address: port_name.to_string(), ..Default::default() }
+    }
+
+    /// Reads an endpoint URL, i.e. `udp://10.0.0.5:5000?local=5001`
+    pub fn parse(text: &str) -> Result<Endpoint, String> {
+        let text = text.trim();
+        let Some((scheme, rest)) = text.split_once("://") else {
+            return Ok(Endpoint::serial(text));
+        };
+        let kind = NETWORK_KINDS
+            .into_iter()
+            .find(|kind| kind.scheme() == Some(scheme))
+            .ok_or_else(|| format!("Unknown transport '{}'", scheme))?;
+        let (address, local_port) = match rest.split_once("?local=") {
+            Some((address, port)) if kind == TransportKind::Udp => {
+                let port = port.parse().map_err(|_| format!("Invalid local port '{}'", port))?;
+                (address, port)
+            }
+            _ => (rest, 0),
+        };
+        if address.is_empty() {
+            return Err(format!("No address in '{}'", text));
+        }
+        Ok(Endpoint { kind, address: address.to_string(), local_port })
+    }
+
+    pub fn is_serial(&self) -> bool {
+        self.kind == TransportKind::Serial
+    }
+
+    /// Whether the line settings and control lines apply, to a local or a remote serial port
+    pub fn has_line_settings(&self) -> bool {
+        matches!(self.kind, TransportKind::Serial | TransportKind::Rfc2217)
+    }
+
+    /// Opens the transport, blocking until it is connected. A TCP server gives up waiting for
+    /// its client once `stop` is set.
+    pub fn open(
+        &self,
+        config: &SerialConfig,
+        stop: &AtomicBool
+    ) -> Result<Box<dyn Transport>, String> {
+        let timeout = Some(Duration::from_millis(config.timeout_ms.max(1)));
+        let failed = |e: io::Error| format!("Failed to connect to {}: {}", self, e);
+        match self.kind {
+            TransportKind::Serial => {
+                let port = config
+                    .builder(&self.address)
+                    .open()
+                    .map_err(|e| format!("Failed to open serial port: {}", e))?;
+                Ok(Box::new(port))
+            }
+            TransportKind::Rfc2217 => Ok(Box::new(Rfc2217::open(&self.address, config)?)),
+            TransportKind::TcpClient => {
+                let stream = connect_tcp(&self.address).map_err(failed)?;
+                stream.set_read_timeout(timeout).map_err(failed)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(failed)?;
+                let _ = stream.set_nodelay(true);
+                Ok(Box::new(stream))
+            }
+            TransportKind::TcpServer => {
+                let stream = accept_tcp(&self.address, stop).map_err(
+                    |e| format!("Failed to listen on {}: {}", self, e)
+                )?;
+                stream.set_read_timeout(timeout).map_err(failed)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(failed)?;
+                let _ = stream.set_nodelay(true);
+                Ok(Box::new(stream))
+            }
+            TransportKind::Udp => {
+                let peer = self.address
+                    .to_socket_addrs()
+                    .map_err(failed)?
+                    .next()
+                    .ok_or_else(|| format!("Failed to connect to {}: no address found", self))?;
+                // the local socket has to be of the address family of the peer
+                let local: SocketAddr = match peer {
+                    SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, self.local_port).into(),
+                    SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, self.local_port).into(),
+                };
+                let socket = UdpSocket::bind(local).map_err(failed)?;
+                socket.connect(peer).map_err(failed)?;
+                socket.set_read_timeout(timeout).map_err(failed)?;
+                socket.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(failed)?;
+                Ok(Box::new(UdpTransport(socket)))
+            }
+            #[cfg(unix)]
+            TransportKind::Unix => {
+                let stream = std::os::unix::net::UnixStream
+                    ::connect(&self.address)
+                    .map_err(failed)?;
+                stream.set_read_timeout(timeout).map_err(failed)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(failed)?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(not(unix))]
+            TransportKind::Unix => Err("Unix domain sockets need a Unix system".to_string()),
+        }
+    }
+}
+
+/// Connects to the first address `address` resolves to that accepts
+pub fn connect_tcp(address: &str) -> io::Result<TcpStream> {
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no address found");
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                return Ok(stream);
+            }
+            Err(e) => {
+                error = e;
+            }
+        }
+    }
+    Err(error)
+}
+
+/// Listens on `address` until one client connects, or `stop` is set
+fn accept_tcp(address: &str, stop: &AtomicBool) -> io::Result<TcpStream> {
+    let listener = TcpListener::bind(address)?;
+    listener.set_nonblocking(true)?;
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                return Ok(stream);
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if stop.load(Ordering::SeqCst) {
+                    return Err(io::Error::new(io::ErrorKind::Interrupted, "stopped waiting"));
+                }
+                std::thread::sleep(ACCEPT_POLL);
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> SerialConfig {
+        SerialConfig { timeout_ms: 1000, ..SerialConfig::new() }
+    }
+
+    fn read_some(transport: &mut Box<dyn Transport>) -> Vec<u8> {
+        let mut buffer = [0u8; 64];
+        let count = transport.read(&mut buffer).unwrap();
+        buffer[..count].to_vec()
+    }
+
+    #[test]
+    fn parse_endpoints() {
+        assert_eq!(Endpoint::parse("/dev/ttyUSB0").unwrap(), Endpoint::serial("/dev/ttyUSB0"));
+        for url in [
+            "tcp://localhost:2000",
+            "rfc2217://lab-server:4001",
+            "tcp-server://0.0.0.0:2000",
+            "udp://10.0.0.5:5000?local=5001",
+            "unix:///tmp/uart.sock",
+            "COM3",
+        ] {
+            assert_eq!(Endpoint::parse(url).unwrap().to_string(), url);
+        }
+        let udp = Endpoint::parse("udp://10.0.0.5:5000?local=5001").unwrap();
+        assert_eq!(udp.kind, TransportKind::Udp);
+        assert_eq!(udp.address, "10.0.0.5:5000");
+        assert_eq!(udp.local_port, 5001);
+        assert!(Endpoint::parse("ftp://host").is_err());
+        assert!(Endpoint::parse("tcp://").is_err());
+    }
+
+    #[test]
+    fn tcp_client_and_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let client = Endpoint::parse(&format!("tcp://{}", address)).unwrap();
+        let mut transport = client.open(&config(), &AtomicBool::new(false)).unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+
+        peer.write_all(b"hello\n").unwrap();
+        assert_eq!(read_some(&mut transport), b"hello\n");
+        transport.try_clone().unwrap().write_all(b"ping\n").unwrap();
+        let mut buffer = [0u8; 5];
+        peer.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"ping\n");
+        assert!(transport.set_config(&config()).is_err());
+
+        // a server hands over the first client, and gives up once asked to stop
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let server = Endpoint::parse(&format!("tcp-server://127.0.0.1:{}", port)).unwrap();
+        let opener = std::thread::spawn(move || {
+            server.open(&config(), &AtomicBool::new(false)).map(|mut transport| {
+                read_some(&mut transport)
+            })
+        });
+        let mut peer = loop {
+            match TcpStream::connect(("127.0.0.1", port)) {
+                Ok(stream) => break stream,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        peer.write_all(b"sim").unwrap();
+        assert_eq!(opener.join().unwrap().unwrap(), b"sim");
+
+        let server = Endpoint::parse("tcp-server://127.0.0.1:0").unwrap();
+        assert!(server.open(&config(), &AtomicBool::new(true)).is_err());
+    }
+
+    #[test]
+    fn udp_datagrams() {
+        for address in ["127.0.0.1:0", "[::1]:0"] {
+            // a host without IPv6 has nothing to test for it
+            let Ok(peer) = UdpSocket::bind(address) else {
+                continue;
+            };
+            let url = format!("udp://{}", peer.local_addr().unwrap());
+            let endpoint = Endpoint::parse(&url).unwrap();
+            let mut transport = endpoint.open(&config(), &AtomicBool::new(false)).unwrap();
+
+            transport.write_all(b"ping").unwrap();
+            let mut buffer = [0u8; 16];
+            let (count, from) = peer.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..count], b"ping");
+            peer.send_to(b"pong", from).unwrap();
+            assert_eq!(read_some(&mut transport), b"pong");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        let path = std::env::temp_dir().join(format!("arrakis-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let endpoint = Endpoint::parse(&format!("unix://{}", path.display())).unwrap();
+        let mut transport = endpoint.open(&config(), &AtomicBool::new(false)).unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+
+        peer.write_all(b"uart").unwrap();
+        assert_eq!(read_some(&mut transport), b"uart");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,316 @@
+//! Trigger rules, regexes matched against every received line that fire actions, i.e. count
+//! and bookmark the assertion messages of a soak run and pause the terminal on the first one
+
+use std::collections::BTreeMap;
+
+use chrono::{ DateTime, Local };
+use regex::Regex;
+use serde::{ Deserialize, Serialize };
+
+use crate::models::highlight::{ HighlightRule, Rgb };
+use crate::models::scrollback::{ Chunk, Direction, LineSplitter };
+
+/// What a trigger does to the data log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogAction {
+    #[default]
+    None,
+    Start,
+    Stop,
+}
+
+pub const LOG_ACTIONS: [LogAction; 3] = [LogAction::None, LogAction::Start, LogAction::Stop];
+
+impl LogAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogAction::None => "-",
+            LogAction::Start => "Start",
+            LogAction::Stop => "Stop",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriggerRule {
+    pub name: String,
+    pub enabled: bool,
+    pub pattern: String,
+    pub case_sensitive: bool,
+    /// Background of the matches in the terminal
+    pub highlight: Option<Rgb>,
+    /// Adds the line to the bookmarks of the Home tab
+    pub bookmark: bool,
+    /// Pops up the line
+    pub toast: bool,
+    pub log: LogAction,
+    /// Line sent back, `$1` or `${name}` are replaced by the groups of the pattern
+    pub send: String,
+    /// Holds the data back from the terminal until resumed
+    pub pause: bool,
+    /// Counts the lines on the Home tab
+    pub count: bool,
+}
+
+impl Default for TriggerRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            pattern: String::new(),
+            case_sensitive: true,
+            highlight: None,
+            bookmark: false,
+            toast: false,
+            log: LogAction::None,
+            send: String::new(),
+            pause: false,
+            count: false,
+        }
+    }
+}
+
+impl TriggerRule {
+    /// The highlight rule colouring the matches of the trigger, when it has a colour
+    pub fn highlight_rule(&self) -> Option<HighlightRule> {
+        Some(HighlightRule {
+            name: self.name.clone(),
+            enabled: self.enabled,
+            pattern: self.pattern.clone(),
+            case_sensitive: self.case_sensitive,
+            background: Some(self.highlight?),
+            ..Default::default()
+        })
+    }
+}
+
+/// What a trigger asks of the application, counting is done by `Triggers` itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Bookmark,
+    Toast,
+    StartLogging,
+    StopLogging,
+    Send(String),
+    Pause,
+}
+
+/// A trigger that matched a received line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fired {
+    /// Name of the trigger
+    pub name: String,
+    pub at: DateTime<Local>,
+    /// The line, without escape sequences
+    pub text: String,
+    /// Lines the chunk completed after this one
+    pub lines_after: usize,
+    pub actions: Vec<Action>,
+}
+
+/// The enabled triggers of the project, matched against the lines received
+#[derive(Debug, Default)]
+pub struct Triggers {
+    /// What the triggers were built from, to notice edits
+    pub configs: Vec<TriggerRule>,
+    rules: Vec<(Regex, TriggerRule)>,
+    /// Triggers whose pattern does not compile
+    pub errors: Vec<String>,
+    /// Highlight rules of the triggers with a colour, drawn after the project's
+    pub highlights: Vec<HighlightRule>,
+    /// Lines counted by each counting trigger, by name, kept across edits
+    pub counts: BTreeMap<String, u64>,
+    lines: LineSplitter,
+}
+
+impl Triggers {
+    pub fn new() -> Triggers {
+        Triggers { lines: LineSplitter::new(), ..Default::default() }
+    }
+
+    /// Ends lines at carriage returns not followed by a line feed
+    pub fn set_cr_newline(&mut self, cr_newline: bool) {
+        self.lines.set_cr_newline(cr_newline);
+    }
+
+    /// Builds the enabled triggers of `configs`, keeping the counts and the line being
+    /// received
+    pub fn set_configs(&mut self, configs: &[TriggerRule]) {
+        self.rules.clear();
+        self.errors.clear();
+        self.highlights.clear();
+        for config in configs.iter().filter(|config| config.enabled && !config.pattern.is_empty()) {
+            let pattern = if config.case_sensitive {
+                config.pattern.clone()
+            } else {
+                format!("(?i){}", config.pattern)
+            };
+            match Regex::new(&pattern) {
+                Ok(regex) => {
+                    if config.count {
+                        self.counts.entry(config.name.clone()).or_default();
+                    }
+                    self.highlights.extend(config.highlight_rule());
+                    self.rules.push((regex, config.clone()));
+                }
+                Err(e) => self.errors.push(format!("Trigger {}: {}", config.name, e)),
+            }
+        }
+        self.configs = configs.to_vec();
+    }
+
+    /// The triggers fired by the received lines `chunk` completes, in order
+    pub fn push(&mut self, chunk: &Chunk) -> Vec<Fired> {
+        if chunk.direction != Direction::Rx || self.rules.is_empty() {
+            return Vec::new();
+        }
+        let mut fired = Vec::new();
+        let lines = self.lines.push(chunk);
+        let count = lines.len();
+        for (index, line) in lines.into_iter().enumerate() {
+            for (regex, rule) in &self.rules {
+                let Some(captures) = regex.captures(&line.text) else {
+                    continue;
+                };
+                if rule.count {
+                    *self.counts.entry(rule.name.clone()).or_default() += 1;
+                }
+                let mut actions = Vec::new();
+                if rule.bookmark {
+                    actions.push(Action::Bookmark);
+                }
+                if rule.toast {
+                    actions.push(Action::Toast);
+                }
+                match rule.log {
+                    LogAction::Start => actions.push(Action::StartLogging),
+                    LogAction::Stop => actions.push(Action::StopLogging),
+                    LogAction::None => {}
+                }
+                if !rule.send.is_empty() {
+                    let mut response = String::new();
+                    captures.expand(&rule.send, &mut response);
+                    actions.push(Action::Send(response));
+                }
+                if rule.pause {
+                    actions.push(Action::Pause);
+                }
+                fired.push(Fired {
+                    name: rule.name.clone(),
+                    at: line.at,
+                    text: line.text.clone(),
+                    lines_after: count - index - 1,
+                    actions,
+                });
+            }
+        }
+        fired
+    }
+
+    pub fn reset_counts(&mut self) {
+        for count in self.counts.values_mut() {
+            *count = 0;
+        }
+    }
+}
+
+/// A line kept by a bookmarking trigger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub name: String,
+    pub at: DateTime<Local>,
+    pub text: String,
+    /// Number of the terminal line it was shown on, as in `Scrollback::first_line`, with the
+    /// generation of the view. None while the terminal is paused.
+    pub line: Option<(u64, usize)>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rx(bytes: &[u8]) -> Chunk {
+        Chunk::new(Direction::Rx, bytes.to_vec())
+    }
+
+    #[test]
+    fn fire_actions() {
+        let rules = [
+            TriggerRule {
+                name: "assert".to_string(),
+                pattern: r"ASSERT (\w+)".to_string(),
+                bookmark: true,
+                send: "dump $1".to_string(),
+                pause: true,
+                count: true,
+                ..Default::default()
+            },
+            TriggerRule {
+                name: "boot".to_string(),
+                pattern: "booting".to_string(),
+                case_sensitive: false,
+                log: LogAction::Start,
+                toast: true,
+                ..Default::default()
+            },
+            TriggerRule {
+                name: "off".to_string(),
+                pattern: "ASSERT".to_string(),
+                enabled: false,
+                ..Default::default()
+            },
+        ];
+        let mut triggers = Triggers::new();
+        triggers.set_configs(&rules);
+        assert!(triggers.errors.is_empty());
+        assert_eq!(triggers.counts.get("assert"), Some(&0));
+
+        let fired = triggers.push(&rx(b"Booting v1\r\nASSERT st"));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].actions, vec![Action::Toast, Action::StartLogging]);
+        let fired = triggers.push(&rx(b"ack failed\r\n"));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].name, "assert");
+        assert_eq!(fired[0].text, "ASSERT stack failed");
+        assert_eq!(fired[0].lines_after, 0);
+        assert_eq!(
+            fired[0].actions,
+            vec![Action::Bookmark, Action::Send("dump stack".to_string()), Action::Pause]
+        );
+        assert_eq!(triggers.counts.get("assert"), Some(&1));
+
+        // sent lines and application messages never fire
+        assert!(triggers.push(&Chunk::new(Direction::Tx, b"ASSERT x\n".to_vec())).is_empty());
+        assert!(triggers.push(&Chunk::new(Direction::Local, b"ASSERT x\n".to_vec())).is_empty());
+
+        // counts survive edits of the rules
+        triggers.set_configs(&rules[..1]);
+        triggers.push(&rx(b"\x1b[31mASSERT heap\x1b[0m\n"));
+        assert_eq!(triggers.counts.get("assert"), Some(&2));
+        triggers.reset_counts();
+        assert_eq!(triggers.counts.get("assert"), Some(&0));
+
+        triggers.set_configs(&[TriggerRule {
+            name: "broken".to_string(),
+            pattern: "(".to_string(),
+            ..Default::default()
+        }]);
+        assert!(triggers.errors[0].starts_with("Trigger broken:"));
+    }
+
+    #[test]
+    fn highlight_rule() {
+        let mut rule = TriggerRule { pattern: "ASSERT".to_string(), ..Default::default() };
+        assert!(rule.highlight_rule().is_none());
+        rule.highlight = Some(Rgb([0x62, 0x72, 0xa4]));
+        let highlight = rule.highlight_rule().unwrap();
+        assert_eq!(highlight.background, rule.highlight);
+        assert_eq!(highlight.pattern, "ASSERT");
+
+        let mut triggers = Triggers::new();
+        triggers.set_configs(&[rule]);
+        assert_eq!(triggers.highlights, vec![highlight]);
+    }
+}